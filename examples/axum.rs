@@ -4,11 +4,6 @@
 //!
 //! Run with: cargo run --example axum
 
-use std::sync::Arc;
-
-use sea_orm::Database;
-use sea_orm_tracing::prelude::*;
-
 // This example shows the pattern, but won't compile without axum dependencies.
 // Add these to Cargo.toml to run:
 //
@@ -0,0 +1,245 @@
+//! Bounded cache of parsed statements.
+//!
+//! SeaORM emits the same statement text over and over, so parsing every statement
+//! again is wasted work. The cache maps a hash of the SQL text and backend to the shared
+//! [`ParsedSql`]. It is split into shards, each behind its own mutex, so concurrent
+//! queries rarely contend on the same lock.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use sea_orm::DbBackend;
+
+use crate::parser::{fnv1a, ParsedSql, SqlClassifier};
+
+/// Number of independently locked shards.
+///
+/// Caches smaller than this use one shard per entry, so they never hold more
+/// statements than configured.
+const SHARDS: usize = 16;
+
+/// Statements longer than this are parsed without being cached.
+///
+/// Long statements are usually bulk inserts whose text changes with the batch size,
+/// so caching them would only evict hot entries.
+const MAX_CACHED_LENGTH: usize = 8 * 1024;
+
+/// Hit and miss counters of a parse cache.
+///
+/// Returned by [`TracedConnection::parse_cache_stats`](crate::TracedConnection::parse_cache_stats).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ParseCacheStats {
+    /// Number of statements served from the cache.
+    pub hits: u64,
+    /// Number of statements that had to be parsed.
+    pub misses: u64,
+    /// Number of statements currently cached.
+    pub entries: usize,
+}
+
+#[derive(Debug)]
+struct Entry {
+    backend: DbBackend,
+    sql: Box<str>,
+    parsed: Arc<ParsedSql>,
+}
+
+/// A bounded, sharded cache of [`ParsedSql`] keyed by the SQL text and backend.
+///
+/// Statements are classified by the configured [`SqlClassifier`], if any, before they
+/// are cached.
+#[derive(Debug)]
+pub(crate) struct ParseCache {
+    shards: Box<[Mutex<HashMap<u64, Entry>>]>,
+    capacity: usize,
+    classifier: Option<Arc<dyn SqlClassifier>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl ParseCache {
+    /// Create a cache holding up to `capacity` statements.
+    ///
+    /// A capacity of zero disables caching.
+    pub(crate) fn new(capacity: usize, classifier: Option<Arc<dyn SqlClassifier>>) -> Self {
+        Self {
+            shards: (0..capacity.min(SHARDS))
+                .map(|_| Mutex::default())
+                .collect(),
+            capacity,
+            classifier,
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    /// Return the parsed form of `sql`, parsing and caching it on a miss.
+    pub(crate) fn parse(&self, sql: &str, backend: DbBackend) -> Arc<ParsedSql> {
+        if self.shards.is_empty() || sql.len() > MAX_CACHED_LENGTH {
+            self.misses.fetch_add(1, Ordering::Relaxed);
+            return Arc::new(self.classify(sql, backend));
+        }
+
+        let key = cache_key(sql, backend);
+        let index = key as usize % self.shards.len();
+        let shard = &self.shards[index];
+
+        if let Some(entry) = lock(shard).get(&key) {
+            // Two statements can share a hash; only reuse an exact match
+            if entry.backend == backend && *entry.sql == *sql {
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                return entry.parsed.clone();
+            }
+        }
+
+        // Parse outside the lock so other statements in the shard are not blocked
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        let parsed = Arc::new(self.classify(sql, backend));
+
+        let mut entries = lock(shard);
+        if entries.len() >= self.shard_capacity(index) && !entries.contains_key(&key) {
+            // Evict an arbitrary entry; hot statements are re-inserted on their next miss
+            if let Some(&evicted) = entries.keys().next() {
+                entries.remove(&evicted);
+            }
+        }
+        entries.insert(
+            key,
+            Entry {
+                backend,
+                sql: sql.into(),
+                parsed: parsed.clone(),
+            },
+        );
+        parsed
+    }
+
+    /// Number of statements the shard at `index` may hold.
+    ///
+    /// The capacity is split as evenly as possible, so the shards add up to exactly
+    /// the configured capacity.
+    fn shard_capacity(&self, index: usize) -> usize {
+        let shards = self.shards.len();
+        self.capacity / shards + usize::from(index < self.capacity % shards)
+    }
+
+    fn classify(&self, sql: &str, backend: DbBackend) -> ParsedSql {
+        match &self.classifier {
+            Some(classifier) => ParsedSql::parse_with(sql, backend, classifier.as_ref()),
+            None => ParsedSql::parse(sql, backend),
+        }
+    }
+
+    /// Current hit and miss counts and the number of cached statements.
+    pub(crate) fn stats(&self) -> ParseCacheStats {
+        ParseCacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            entries: self.shards.iter().map(|shard| lock(shard).len()).sum(),
+        }
+    }
+}
+
+/// Cache key of `sql` parsed for `backend`.
+///
+/// The parse depends on the backend (identifier quoting, dialect), so the same SQL run
+/// on two backends gets an entry for each.
+fn cache_key(sql: &str, backend: DbBackend) -> u64 {
+    let tag: u8 = match backend {
+        DbBackend::Postgres => 0,
+        DbBackend::MySql => 1,
+        DbBackend::Sqlite => 2,
+    };
+    // One more FNV-1a round over the backend tag
+    (fnv1a(sql.as_bytes()) ^ u64::from(tag)).wrapping_mul(0x0100_0000_01b3)
+}
+
+/// Lock a shard, recovering from a panic in another thread while it was held.
+fn lock(shard: &Mutex<HashMap<u64, Entry>>) -> std::sync::MutexGuard<'_, HashMap<u64, Entry>> {
+    shard
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hits_and_misses() {
+        let cache = ParseCache::new(64, None);
+        let first = cache.parse("SELECT * FROM users WHERE id = $1", DbBackend::Postgres);
+        let second = cache.parse("SELECT * FROM users WHERE id = $1", DbBackend::Postgres);
+        assert!(Arc::ptr_eq(&first, &second));
+        assert_eq!(second.span_name(), "SELECT users");
+
+        // Each backend keeps its own entry instead of evicting the other's
+        let mysql = cache.parse("SELECT * FROM users WHERE id = $1", DbBackend::MySql);
+        assert!(!Arc::ptr_eq(&first, &mysql));
+        cache.parse("SELECT * FROM users WHERE id = $1", DbBackend::Postgres);
+        cache.parse("SELECT * FROM users WHERE id = $1", DbBackend::MySql);
+        assert_eq!(
+            cache.stats(),
+            ParseCacheStats {
+                hits: 3,
+                misses: 2,
+                entries: 2,
+            }
+        );
+    }
+
+    #[test]
+    fn test_bounded() {
+        let cache = ParseCache::new(32, None);
+        for i in 0..1000 {
+            cache.parse(&format!("SELECT * FROM t{}", i), DbBackend::Postgres);
+        }
+        let stats = cache.stats();
+        assert_eq!(stats.misses, 1000);
+        assert!(stats.entries <= 32);
+    }
+
+    #[test]
+    fn test_capacity_below_shard_count() {
+        for capacity in [1, 5, 17] {
+            let cache = ParseCache::new(capacity, None);
+            for i in 0..1000 {
+                cache.parse(&format!("SELECT * FROM t{}", i), DbBackend::Postgres);
+            }
+            assert!(cache.stats().entries <= capacity);
+        }
+
+        let cache = ParseCache::new(1, None);
+        cache.parse("SELECT * FROM users", DbBackend::Postgres);
+        cache.parse("SELECT * FROM orders", DbBackend::Postgres);
+        assert_eq!(cache.stats().entries, 1);
+    }
+
+    #[test]
+    fn test_classifier() {
+        let cache = ParseCache::new(
+            64,
+            Some(Arc::new(|_: &str, _: DbBackend, parsed: &mut ParsedSql| {
+                parsed.table = parsed.table.take().map(|t| t.to_uppercase());
+            })),
+        );
+        let parsed = cache.parse("DELETE FROM sessions", DbBackend::Postgres);
+        assert_eq!(parsed.table.as_deref(), Some("SESSIONS"));
+        assert_eq!(parsed.span_name(), "DELETE SESSIONS");
+    }
+
+    #[test]
+    fn test_disabled_and_long_statements() {
+        let disabled = ParseCache::new(0, None);
+        disabled.parse("SELECT 1", DbBackend::Postgres);
+        disabled.parse("SELECT 1", DbBackend::Postgres);
+        assert_eq!(disabled.stats().hits, 0);
+        assert_eq!(disabled.stats().entries, 0);
+
+        let cache = ParseCache::new(64, None);
+        let long = format!("SELECT {} FROM t", "1, ".repeat(4096));
+        cache.parse(&long, DbBackend::Postgres);
+        assert_eq!(cache.stats().entries, 0);
+    }
+}
@@ -1,7 +1,18 @@
 //! Configuration for tracing behavior.
 
+use std::sync::Arc;
 use std::time::Duration;
 
+use once_cell::sync::Lazy;
+
+use crate::parser::SqlClassifier;
+
+/// Default number of parsed statements cached per connection.
+const DEFAULT_PARSE_CACHE_SIZE: usize = 1024;
+
+/// The conventions selected by `OTEL_SEMCONV_STABILITY_OPT_IN`, read once per process.
+static ENV_SEMCONV: Lazy<SemconvVersion> = Lazy::new(SemconvVersion::from_env);
+
 /// Configuration options for database tracing.
 ///
 /// # Example
@@ -13,6 +24,13 @@ use std::time::Duration;
 /// let config = TracingConfig::default()
 ///     .with_statement_logging(true)
 ///     .with_slow_query_threshold(Duration::from_millis(100));
+///
+/// // The fields are public, so struct literals work too
+/// let config = TracingConfig {
+///     log_statements: true,
+///     max_statement_length: Some(4096),
+///     ..Default::default()
+/// };
 /// ```
 #[derive(Debug, Clone)]
 pub struct TracingConfig {
@@ -20,19 +38,48 @@ pub struct TracingConfig {
     /// Default: `false` (for security - prevents accidental credential logging)
     pub log_statements: bool,
 
+    /// How the SQL statement is recorded when statement logging is enabled.
+    /// Default: [`StatementMode::Raw`]
+    pub statement_mode: StatementMode,
+
+    /// Maximum length in bytes of the SQL recorded in `db.statement`.
+    /// Longer statements are truncated and marked with their original length.
+    /// Default: `None` (no limit)
+    pub max_statement_length: Option<usize>,
+
     /// Whether to include query parameters in spans.
+    /// Bound values are recorded as `db.query.parameter.<index>` attributes, see
+    /// [`TracingConfig::with_parameter_logging`] for large statements.
     /// Default: `false` (parameters may contain sensitive data)
     pub log_parameters: bool,
 
+    /// Redaction applied to query parameters before they are recorded.
+    /// Default: no redaction
+    pub parameter_redaction: ParameterRedaction,
+
     /// Threshold for logging slow queries at WARN level.
     /// Queries exceeding this duration will be logged with additional context.
     /// Default: 500ms
     pub slow_query_threshold: Duration,
 
+    /// Which OpenTelemetry database semantic conventions attribute names follow.
+    /// Default: from `OTEL_SEMCONV_STABILITY_OPT_IN`, read once per process, otherwise
+    /// [`SemconvVersion::Legacy`]
+    pub semconv: SemconvVersion,
+
     /// Whether to record the number of rows affected/returned.
     /// Default: `true`
     pub record_row_counts: bool,
 
+    /// Maximum number of parsed statements cached per connection.
+    /// Set to `0` to parse every statement.
+    /// Default: 1024
+    pub parse_cache_size: usize,
+
+    /// Hook that adjusts the operation, tables and span name of parsed statements.
+    /// Default: `None`
+    pub classifier: Option<Arc<dyn SqlClassifier>>,
+
     /// Target name for tracing events.
     /// Default: "sea_orm_tracing"
     pub target: &'static str,
@@ -61,9 +108,15 @@ impl Default for TracingConfig {
     fn default() -> Self {
         Self {
             log_statements: false,
+            statement_mode: StatementMode::Raw,
+            max_statement_length: None,
             log_parameters: false,
+            parameter_redaction: ParameterRedaction::default(),
             slow_query_threshold: Duration::from_millis(500),
+            semconv: *ENV_SEMCONV,
             record_row_counts: true,
+            parse_cache_size: DEFAULT_PARSE_CACHE_SIZE,
+            classifier: None,
             target: "sea_orm_tracing",
             database_name: None,
             server_address: None,
@@ -88,8 +141,45 @@ impl TracingConfig {
         self
     }
 
+    /// Set how the SQL statement is recorded when statement logging is enabled.
+    ///
+    /// [`StatementMode::Sanitized`] replaces literal values embedded in the SQL text
+    /// with `?`, which keeps `execute_unprepared` and `Statement::from_string` queries
+    /// from leaking data into `db.statement`.
+    ///
+    /// ```rust
+    /// use sea_orm_tracing::{StatementMode, TracingConfig};
+    ///
+    /// let config = TracingConfig::default()
+    ///     .with_statement_logging(true)
+    ///     .with_statement_mode(StatementMode::Sanitized);
+    /// ```
+    pub fn with_statement_mode(mut self, mode: StatementMode) -> Self {
+        self.statement_mode = mode;
+        self
+    }
+
+    /// Truncate statements recorded in `db.statement` to at most `max_bytes` bytes.
+    ///
+    /// Bulk inserts can produce statements hundreds of kilobytes long, which many
+    /// exporters drop. Truncation respects UTF-8 character boundaries and ends with a
+    /// `... [truncated, N bytes]` marker, which counts towards `max_bytes`. `N` is the
+    /// length of the original statement, before sanitizing, which is also recorded in
+    /// `db.statement.length`.
+    pub fn with_max_statement_length(mut self, max_bytes: usize) -> Self {
+        self.max_statement_length = Some(max_bytes);
+        self
+    }
+
     /// Enable or disable parameter logging in spans.
     ///
+    /// Bound values are recorded as `db.query.parameter.<index>` attributes, formatted
+    /// as SQL literals for the database backend. Only the first 16 parameters of a
+    /// statement are declared as span fields. With the `opentelemetry` feature the rest
+    /// are recorded as attributes too; without it they are dropped and
+    /// `db.query.parameter.truncated` is set. The total number is always recorded as
+    /// `db.query.parameter.count`.
+    ///
     /// **Security Warning**: Query parameters often contain user input and
     /// potentially sensitive data. Only enable in development or controlled environments.
     pub fn with_parameter_logging(mut self, enabled: bool) -> Self {
@@ -97,6 +187,44 @@ impl TracingConfig {
         self
     }
 
+    /// Record only the type of each parameter (e.g. `String`, `BigInt`), not its value.
+    pub fn with_parameter_types_only(mut self, enabled: bool) -> Self {
+        self.parameter_redaction.types_only = enabled;
+        self
+    }
+
+    /// Truncate string and byte parameters longer than `max_bytes` bytes.
+    ///
+    /// Truncation respects UTF-8 character boundaries.
+    pub fn with_parameter_max_length(mut self, max_bytes: usize) -> Self {
+        self.parameter_redaction.max_length = Some(max_bytes);
+        self
+    }
+
+    /// Mask parameters bound to the given columns.
+    ///
+    /// Columns are matched case-insensitively against INSERT column lists, `SET`
+    /// assignments and WHERE comparisons. Masked parameters are recorded as
+    /// `[REDACTED]`.
+    ///
+    /// ```rust
+    /// use sea_orm_tracing::TracingConfig;
+    ///
+    /// let config = TracingConfig::default()
+    ///     .with_parameter_logging(true)
+    ///     .with_masked_columns(["password", "email", "ssn"]);
+    /// ```
+    pub fn with_masked_columns<I, S>(mut self, columns: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.parameter_redaction
+            .masked_columns
+            .extend(columns.into_iter().map(Into::into));
+        self
+    }
+
     /// Set the threshold for slow query warnings.
     ///
     /// Queries taking longer than this duration will be logged at WARN level
@@ -106,12 +234,43 @@ impl TracingConfig {
         self
     }
 
+    /// Set which OpenTelemetry database semantic conventions to follow.
+    ///
+    /// ```rust
+    /// use sea_orm_tracing::{SemconvVersion, TracingConfig};
+    ///
+    /// // Emit both the old and the stable attribute names while dashboards migrate
+    /// let config = TracingConfig::default().with_semconv_version(SemconvVersion::Dual);
+    /// ```
+    pub fn with_semconv_version(mut self, version: SemconvVersion) -> Self {
+        self.semconv = version;
+        self
+    }
+
     /// Enable or disable row count recording.
     pub fn with_row_count_recording(mut self, enabled: bool) -> Self {
         self.record_row_counts = enabled;
         self
     }
 
+    /// Set the maximum number of parsed statements cached per connection.
+    ///
+    /// Parsing results are keyed by the SQL text, so repeated statements skip the
+    /// parser. Set to `0` to disable the cache.
+    pub fn with_parse_cache_size(mut self, entries: usize) -> Self {
+        self.parse_cache_size = entries;
+        self
+    }
+
+    /// Register a classifier that adjusts how statements are labeled.
+    ///
+    /// See [`SqlClassifier`] for an example. Classifications are cached along with the
+    /// parsed statement.
+    pub fn with_classifier(mut self, classifier: impl SqlClassifier + 'static) -> Self {
+        self.classifier = Some(Arc::new(classifier));
+        self
+    }
+
     /// Set a custom tracing target name.
     pub fn with_target(mut self, target: &'static str) -> Self {
         self.target = target;
@@ -157,9 +316,15 @@ impl TracingConfig {
     pub fn development() -> Self {
         Self {
             log_statements: true,
+            statement_mode: StatementMode::Raw,
+            max_statement_length: None,
             log_parameters: true,
+            parameter_redaction: ParameterRedaction::default(),
             slow_query_threshold: Duration::from_millis(100),
+            semconv: *ENV_SEMCONV,
             record_row_counts: true,
+            parse_cache_size: DEFAULT_PARSE_CACHE_SIZE,
+            classifier: None,
             target: "sea_orm_tracing",
             database_name: None,
             server_address: None,
@@ -172,9 +337,15 @@ impl TracingConfig {
     pub fn production() -> Self {
         Self {
             log_statements: false,
+            statement_mode: StatementMode::Sanitized,
+            max_statement_length: None,
             log_parameters: false,
+            parameter_redaction: ParameterRedaction::default(),
             slow_query_threshold: Duration::from_secs(1),
+            semconv: *ENV_SEMCONV,
             record_row_counts: true,
+            parse_cache_size: DEFAULT_PARSE_CACHE_SIZE,
+            classifier: None,
             target: "sea_orm_tracing",
             database_name: None,
             server_address: None,
@@ -183,3 +354,125 @@ impl TracingConfig {
         }
     }
 }
+
+/// How the SQL statement is recorded in `db.statement`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum StatementMode {
+    /// Record the SQL exactly as executed.
+    #[default]
+    Raw,
+    /// Replace string, numeric, boolean and hex literals with `?`.
+    Sanitized,
+}
+
+/// Which OpenTelemetry database semantic conventions span attributes follow.
+///
+/// | Legacy | Stable |
+/// |--------|--------|
+/// | `db.system` | `db.system.name` |
+/// | `db.operation` | `db.operation.name` |
+/// | `db.sql.table` | `db.collection.name` |
+/// | `db.statement` | `db.query.text` |
+/// | `db.rows_affected` (queries) | `db.response.returned_rows` |
+/// | `db.name` | `db.namespace` |
+///
+/// Stable mode also records the span name as `db.query.summary`. `db.namespace` and the
+/// attributes specific to this crate are recorded in every mode, as is `db.rows_affected`
+/// for statements run with `execute`, which return no rows.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SemconvVersion {
+    /// The experimental attribute names used before the conventions were stabilized.
+    #[default]
+    Legacy,
+    /// The stable attribute names.
+    Stable,
+    /// Both the legacy and the stable attribute names, for migrating dashboards.
+    Dual,
+}
+
+impl SemconvVersion {
+    /// Read the version from the `OTEL_SEMCONV_STABILITY_OPT_IN` environment variable.
+    ///
+    /// `database` selects [`Stable`](Self::Stable) and `database/dup` selects
+    /// [`Dual`](Self::Dual), as in the other OpenTelemetry instrumentations. Anything
+    /// else selects [`Legacy`](Self::Legacy).
+    pub fn from_env() -> Self {
+        std::env::var("OTEL_SEMCONV_STABILITY_OPT_IN")
+            .map(|value| Self::from_opt_in(&value))
+            .unwrap_or_default()
+    }
+
+    /// Parse a comma-separated `OTEL_SEMCONV_STABILITY_OPT_IN` value.
+    fn from_opt_in(value: &str) -> Self {
+        let categories: Vec<&str> = value.split(',').map(str::trim).collect();
+        if categories.contains(&"database/dup") {
+            SemconvVersion::Dual
+        } else if categories.contains(&"database") {
+            SemconvVersion::Stable
+        } else {
+            SemconvVersion::Legacy
+        }
+    }
+
+    /// Returns `true` if the legacy attribute names are recorded.
+    pub(crate) fn legacy(self) -> bool {
+        matches!(self, SemconvVersion::Legacy | SemconvVersion::Dual)
+    }
+
+    /// Returns `true` if the stable attribute names are recorded.
+    pub(crate) fn stable(self) -> bool {
+        matches!(self, SemconvVersion::Stable | SemconvVersion::Dual)
+    }
+}
+
+/// Redaction applied to bound parameters when parameter logging is enabled.
+///
+/// The options can be combined: masked columns always win, then `types_only`,
+/// then truncation.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ParameterRedaction {
+    /// Record only the type of each parameter, not its value.
+    /// Default: `false`
+    pub types_only: bool,
+
+    /// Truncate string and byte parameters longer than this many bytes.
+    /// Default: `None`
+    pub max_length: Option<usize>,
+
+    /// Mask parameters bound to these columns (matched case-insensitively).
+    /// Default: empty
+    pub masked_columns: Vec<String>,
+}
+
+impl ParameterRedaction {
+    /// Returns `true` if parameters bound to `column` should be masked.
+    pub fn is_masked(&self, column: &str) -> bool {
+        self.masked_columns
+            .iter()
+            .any(|masked| masked.eq_ignore_ascii_case(column))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_semconv_opt_in() {
+        assert_eq!(SemconvVersion::from_opt_in(""), SemconvVersion::Legacy);
+        assert_eq!(SemconvVersion::from_opt_in("http"), SemconvVersion::Legacy);
+        assert_eq!(
+            SemconvVersion::from_opt_in("http, database"),
+            SemconvVersion::Stable
+        );
+        assert_eq!(
+            SemconvVersion::from_opt_in("database/dup,http"),
+            SemconvVersion::Dual
+        );
+        // The duplicate mode wins when both are given
+        assert_eq!(
+            SemconvVersion::from_opt_in("database,database/dup"),
+            SemconvVersion::Dual
+        );
+    }
+}
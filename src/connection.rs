@@ -11,10 +11,13 @@ use sea_orm::{
     ExecResult, IsolationLevel, QueryResult, Statement, StreamTrait, TransactionError,
     TransactionTrait,
 };
-use tracing::{field, Instrument, Span};
+use tracing::{Instrument, Span};
 
+use crate::cache::{ParseCache, ParseCacheStats};
 use crate::config::TracingConfig;
-use crate::parser::ParsedSql;
+use crate::entity::EntityInfo;
+use crate::instrument::{self, RowCount};
+use crate::transaction::{self, TracedTransaction};
 
 /// A traced wrapper around SeaORM's `DatabaseConnection`.
 ///
@@ -22,6 +25,15 @@ use crate::parser::ParsedSql;
 /// making it a drop-in replacement for `DatabaseConnection`. All database operations
 /// are automatically instrumented with tracing spans.
 ///
+/// The wrapped connection defaults to `DatabaseConnection`, but any `ConnectionTrait`
+/// implementation can be traced, such as a `DatabaseTransaction` obtained elsewhere,
+/// a `MockDatabase` connection in tests, or your own connection type. `StreamTrait`
+/// and `TransactionTrait` are implemented whenever the inner type implements them.
+///
+/// Transactions started with [`TracedConnection::begin`] or
+/// [`TracedConnection::transaction`] are wrapped in a [`TracedTransaction`], so the
+/// statements executed inside them are traced too.
+///
 /// # Span Nesting
 ///
 /// Spans created by `TracedConnection` automatically become children of the current
@@ -42,27 +54,37 @@ use crate::parser::ParsedSql;
 /// let users = Users::find().all(&traced).await?;
 /// ```
 #[derive(Debug, Clone)]
-pub struct TracedConnection {
-    inner: DatabaseConnection,
+pub struct TracedConnection<C = DatabaseConnection> {
+    inner: C,
     config: Arc<TracingConfig>,
+    cache: Arc<ParseCache>,
+    begin_depth: Option<u32>,
 }
 
-impl TracedConnection {
+impl<C> TracedConnection<C> {
     /// Create a new traced connection with the given configuration.
-    pub fn new(connection: DatabaseConnection, config: TracingConfig) -> Self {
+    ///
+    /// Transactions begun on the connection are traced as top-level transactions. Wrap a
+    /// `DatabaseTransaction` with [`TracedConnection::from_transaction`] instead.
+    pub fn new(connection: C, config: TracingConfig) -> Self {
         Self {
             inner: connection,
+            cache: Arc::new(ParseCache::new(
+                config.parse_cache_size,
+                config.classifier.clone(),
+            )),
             config: Arc::new(config),
+            begin_depth: Some(0),
         }
     }
 
     /// Create a new traced connection with default configuration.
-    pub fn wrap(connection: DatabaseConnection) -> Self {
+    pub fn wrap(connection: C) -> Self {
         Self::new(connection, TracingConfig::default())
     }
 
-    /// Get a reference to the underlying `DatabaseConnection`.
-    pub fn inner(&self) -> &DatabaseConnection {
+    /// Get a reference to the underlying connection.
+    pub fn inner(&self) -> &C {
         &self.inner
     }
 
@@ -71,119 +93,221 @@ impl TracedConnection {
         &self.config
     }
 
-    /// Consume the wrapper and return the inner `DatabaseConnection`.
-    pub fn into_inner(self) -> DatabaseConnection {
-        self.inner
+    /// Get the hit and miss counts of the parse cache.
+    ///
+    /// The cache is shared by clones of this connection and the transactions started
+    /// from it.
+    pub fn parse_cache_stats(&self) -> ParseCacheStats {
+        self.cache.stats()
     }
 
-    /// Get the database backend name for span attributes.
-    fn db_system(&self) -> &'static str {
-        match self.inner.get_database_backend() {
-            DbBackend::Postgres => "postgresql",
-            DbBackend::MySql => "mysql",
-            DbBackend::Sqlite => "sqlite",
-        }
+    /// Consume the wrapper and return the inner connection.
+    pub fn into_inner(self) -> C {
+        self.inner
     }
+}
 
-    /// Create a tracing span for a database operation.
-    fn create_span(&self, stmt: &Statement) -> Span {
-        let parsed = ParsedSql::parse(&stmt.sql);
-        let span_name = parsed.span_name();
-        let db_system = self.db_system();
-
-        let span = tracing::info_span!(
-            "db.query",
-            otel.name = %span_name,
-            db.system = %db_system,
-            db.operation = %parsed.operation.as_str(),
-            db.sql.table = field::Empty,
-            db.statement = field::Empty,
-            db.rows_affected = field::Empty,
-            db.duration_ms = field::Empty,
-            db.name = field::Empty,
-            server.address = field::Empty,
-            server.port = field::Empty,
-            peer.service = field::Empty,
-            otel.status_code = field::Empty,
-            error.message = field::Empty,
-            slow_query = field::Empty,
-        );
+impl TracedConnection<DatabaseTransaction> {
+    /// Wrap a transaction that is nested `depth` levels deep.
+    ///
+    /// A transaction begun on a `DatabaseConnection` has depth 0, and
+    /// [`TracedTransaction::depth`] reports the depth of a traced one. Transactions begun
+    /// on the wrapped transaction are nested inside it with a savepoint and recorded
+    /// with `db.transaction.depth = depth + 1`.
+    ///
+    /// ```rust,ignore
+    /// let txn = db.begin().await?.into_inner();
+    /// let traced = TracedConnection::from_transaction(txn, 0);
+    ///
+    /// // Recorded with db.transaction.depth = 1
+    /// let nested = traced.begin().await?;
+    /// ```
+    ///
+    /// A transaction wrapped with `with_tracing()` or `From` is not assumed to be top-level:
+    /// the transactions begun on it are traced as savepoints without a depth.
+    pub fn from_transaction(transaction: DatabaseTransaction, depth: u32) -> Self {
+        Self::from_transaction_with_config(transaction, depth, TracingConfig::default())
+    }
 
-        // Record table if available
-        if let Some(table) = &parsed.table {
-            span.record("db.sql.table", table.as_str());
+    /// Wrap a transaction that is nested `depth` levels deep, with custom tracing
+    /// configuration.
+    pub fn from_transaction_with_config(
+        transaction: DatabaseTransaction,
+        depth: u32,
+        config: TracingConfig,
+    ) -> Self {
+        Self {
+            begin_depth: Some(depth + 1),
+            ..Self::new(transaction, config)
         }
+    }
 
-        // Record database name if configured
-        if let Some(db_name) = &self.config.database_name {
-            span.record("db.name", db_name.as_str());
+    /// Wrap a transaction whose depth is unknown.
+    fn nested(transaction: DatabaseTransaction, config: TracingConfig) -> Self {
+        Self {
+            begin_depth: None,
+            ..Self::new(transaction, config)
         }
+    }
+}
 
-        // Record server address and port for X-Ray service map
-        if let Some(addr) = &self.config.server_address {
-            span.record("server.address", addr.as_str());
-        }
-        if let Some(port) = self.config.server_port {
-            span.record("server.port", port as i64);
+impl<C: ConnectionTrait> TracedConnection<C> {
+    /// Create a tracing span for a database operation.
+    fn create_span(&self, stmt: &Statement, entity: Option<&EntityInfo>) -> Span {
+        let backend = self.inner.get_database_backend();
+        let parsed = self.cache.parse(&stmt.sql, backend);
+        let span = instrument::create_span(&self.config, backend, stmt, &parsed);
+        if let Some(entity) = entity {
+            instrument::record_entity(&span, &parsed, entity);
         }
+        span
+    }
 
-        // Record peer service for X-Ray trace map node naming
-        if let Some(peer) = &self.config.peer_service {
-            span.record("peer.service", peer.as_str());
-        }
+    /// Execute a statement, recording `entity` metadata on its span when given.
+    pub(crate) async fn execute_traced(
+        &self,
+        stmt: Statement,
+        entity: Option<&EntityInfo>,
+    ) -> Result<ExecResult, DbErr> {
+        let span = self.create_span(&stmt, entity);
+        let start = Instant::now();
 
-        // Record SQL statement if configured
-        if self.config.log_statements {
-            span.record("db.statement", stmt.sql.as_str());
-        }
+        let result = self.inner.execute(stmt).instrument(span.clone()).await;
 
-        span
+        let row_count = result
+            .as_ref()
+            .ok()
+            .map(|r| RowCount::Affected(r.rows_affected()));
+        instrument::record_last_insert_id(&span, self.get_database_backend(), &result);
+        self.record_result(&span, &result, start, row_count);
+
+        result
+    }
+
+    /// Query one row, recording `entity` metadata on its span when given.
+    pub(crate) async fn query_one_traced(
+        &self,
+        stmt: Statement,
+        entity: Option<&EntityInfo>,
+    ) -> Result<Option<QueryResult>, DbErr> {
+        let span = self.create_span(&stmt, entity);
+        let start = Instant::now();
+
+        let result = self.inner.query_one(stmt).instrument(span.clone()).await;
+
+        let row_count = result
+            .as_ref()
+            .ok()
+            .map(|opt| RowCount::Returned(opt.is_some() as u64));
+        self.record_result(&span, &result, start, row_count);
+
+        result
+    }
+
+    /// Query all rows, recording `entity` metadata on its span when given.
+    pub(crate) async fn query_all_traced(
+        &self,
+        stmt: Statement,
+        entity: Option<&EntityInfo>,
+    ) -> Result<Vec<QueryResult>, DbErr> {
+        let span = self.create_span(&stmt, entity);
+        let start = Instant::now();
+
+        let result = self.inner.query_all(stmt).instrument(span.clone()).await;
+
+        let row_count = result
+            .as_ref()
+            .ok()
+            .map(|rows| RowCount::Returned(rows.len() as u64));
+        self.record_result(&span, &result, start, row_count);
+
+        result
     }
 
     /// Record the result of a database operation in the span.
-    fn record_result<T, E: std::fmt::Display>(
+    fn record_result<T>(
         &self,
         span: &Span,
-        result: &Result<T, E>,
+        result: &Result<T, DbErr>,
         start: Instant,
-        row_count: Option<u64>,
+        row_count: Option<RowCount>,
     ) {
-        let duration_ms = start.elapsed().as_millis() as i64;
-        span.record("db.duration_ms", duration_ms);
-
-        // Record row count if available and configured
-        if self.config.record_row_counts {
-            if let Some(count) = row_count {
-                span.record("db.rows_affected", count);
-            }
-        }
+        instrument::record_result(&self.config, span, result, start, row_count);
+    }
+}
 
-        // Check for slow query
-        if start.elapsed() > self.config.slow_query_threshold {
-            span.record("slow_query", true);
-            let threshold_ms = self.config.slow_query_threshold.as_millis() as i64;
-            tracing::warn!(
-                parent: span,
-                duration_ms = duration_ms,
-                threshold_ms = threshold_ms,
-                "Slow query detected"
-            );
-        }
+impl<C: ConnectionTrait + TransactionTrait> TracedConnection<C> {
+    /// Begin a traced transaction.
+    ///
+    /// Unlike [`TransactionTrait::begin`], which must return a raw `DatabaseTransaction`,
+    /// this returns a [`TracedTransaction`] so that statements executed inside the
+    /// transaction are traced as well. Method-call syntax (`db.begin()`) resolves to
+    /// this method rather than the trait method.
+    ///
+    /// When the wrapped connection is a transaction, the new transaction is nested inside
+    /// it with a savepoint. Its depth is only known if the transaction was wrapped with
+    /// [`TracedConnection::from_transaction`].
+    pub async fn begin(&self) -> Result<TracedTransaction, DbErr> {
+        self.begin_with_config(None, None).await
+    }
 
-        match result {
-            Ok(_) => {
-                span.record("otel.status_code", "OK");
-            }
-            Err(e) => {
-                span.record("otel.status_code", "ERROR");
-                span.record("error.message", e.to_string().as_str());
-                tracing::error!(
-                    parent: span,
-                    error = %e,
-                    "Database query failed"
-                );
-            }
-        }
+    /// Begin a traced transaction with isolation level and/or access mode.
+    pub async fn begin_with_config(
+        &self,
+        isolation_level: Option<IsolationLevel>,
+        access_mode: Option<AccessMode>,
+    ) -> Result<TracedTransaction, DbErr> {
+        TracedTransaction::start(
+            &self.inner,
+            self.config.clone(),
+            self.cache.clone(),
+            None,
+            self.begin_depth,
+            isolation_level,
+            access_mode,
+        )
+        .await
+    }
+
+    /// Execute the callback inside a traced transaction.
+    ///
+    /// The callback receives a [`TracedTransaction`], so all of its queries are traced.
+    /// If the callback returns an error the transaction is rolled back, otherwise it is
+    /// committed.
+    pub async fn transaction<F, T, E>(&self, callback: F) -> Result<T, TransactionError<E>>
+    where
+        F: for<'c> FnOnce(
+                &'c TracedTransaction,
+            ) -> Pin<Box<dyn Future<Output = Result<T, E>> + Send + 'c>>
+            + Send,
+        T: Send,
+        E: std::fmt::Display + std::fmt::Debug + Send,
+    {
+        let transaction = self.begin().await.map_err(TransactionError::Connection)?;
+        transaction.run(callback).await
+    }
+
+    /// Execute the callback inside a traced transaction with isolation level and/or
+    /// access mode.
+    pub async fn transaction_with_config<F, T, E>(
+        &self,
+        callback: F,
+        isolation_level: Option<IsolationLevel>,
+        access_mode: Option<AccessMode>,
+    ) -> Result<T, TransactionError<E>>
+    where
+        F: for<'c> FnOnce(
+                &'c TracedTransaction,
+            ) -> Pin<Box<dyn Future<Output = Result<T, E>> + Send + 'c>>
+            + Send,
+        T: Send,
+        E: std::fmt::Display + std::fmt::Debug + Send,
+    {
+        let transaction = self
+            .begin_with_config(isolation_level, access_mode)
+            .await
+            .map_err(TransactionError::Connection)?;
+        transaction.run(callback).await
     }
 }
 
@@ -193,37 +317,31 @@ impl From<DatabaseConnection> for TracedConnection {
     }
 }
 
-impl AsRef<DatabaseConnection> for TracedConnection {
-    fn as_ref(&self) -> &DatabaseConnection {
+impl From<DatabaseTransaction> for TracedConnection<DatabaseTransaction> {
+    fn from(transaction: DatabaseTransaction) -> Self {
+        Self::nested(transaction, TracingConfig::default())
+    }
+}
+
+impl<C> AsRef<C> for TracedConnection<C> {
+    fn as_ref(&self) -> &C {
         &self.inner
     }
 }
 
 #[async_trait]
-impl ConnectionTrait for TracedConnection {
+impl<C: ConnectionTrait> ConnectionTrait for TracedConnection<C> {
     fn get_database_backend(&self) -> DbBackend {
         self.inner.get_database_backend()
     }
 
     async fn execute(&self, stmt: Statement) -> Result<ExecResult, DbErr> {
-        let span = self.create_span(&stmt);
-        let start = Instant::now();
-
-        let result = self
-            .inner
-            .execute(stmt)
-            .instrument(span.clone())
-            .await;
-
-        let row_count = result.as_ref().ok().map(|r| r.rows_affected());
-        self.record_result(&span, &result, start, row_count);
-
-        result
+        self.execute_traced(stmt, None).await
     }
 
     async fn execute_unprepared(&self, sql: &str) -> Result<ExecResult, DbErr> {
         let stmt = Statement::from_string(self.get_database_backend(), sql);
-        let span = self.create_span(&stmt);
+        let span = self.create_span(&stmt, None);
         let start = Instant::now();
 
         let result = self
@@ -232,42 +350,22 @@ impl ConnectionTrait for TracedConnection {
             .instrument(span.clone())
             .await;
 
-        let row_count = result.as_ref().ok().map(|r| r.rows_affected());
+        let row_count = result
+            .as_ref()
+            .ok()
+            .map(|r| RowCount::Affected(r.rows_affected()));
+        instrument::record_last_insert_id(&span, self.get_database_backend(), &result);
         self.record_result(&span, &result, start, row_count);
 
         result
     }
 
     async fn query_one(&self, stmt: Statement) -> Result<Option<QueryResult>, DbErr> {
-        let span = self.create_span(&stmt);
-        let start = Instant::now();
-
-        let result = self
-            .inner
-            .query_one(stmt)
-            .instrument(span.clone())
-            .await;
-
-        let row_count = result.as_ref().ok().map(|opt| if opt.is_some() { 1 } else { 0 });
-        self.record_result(&span, &result, start, row_count);
-
-        result
+        self.query_one_traced(stmt, None).await
     }
 
     async fn query_all(&self, stmt: Statement) -> Result<Vec<QueryResult>, DbErr> {
-        let span = self.create_span(&stmt);
-        let start = Instant::now();
-
-        let result = self
-            .inner
-            .query_all(stmt)
-            .instrument(span.clone())
-            .await;
-
-        let row_count = result.as_ref().ok().map(|rows| rows.len() as u64);
-        self.record_result(&span, &result, start, row_count);
-
-        result
+        self.query_all_traced(stmt, None).await
     }
 
     fn support_returning(&self) -> bool {
@@ -280,14 +378,17 @@ impl ConnectionTrait for TracedConnection {
 }
 
 #[async_trait]
-impl StreamTrait for TracedConnection {
-    type Stream<'a> = <DatabaseConnection as StreamTrait>::Stream<'a>;
+impl<C: ConnectionTrait + StreamTrait> StreamTrait for TracedConnection<C> {
+    type Stream<'a>
+        = C::Stream<'a>
+    where
+        Self: 'a;
 
     fn stream<'a>(
         &'a self,
         stmt: Statement,
     ) -> Pin<Box<dyn Future<Output = Result<Self::Stream<'a>, DbErr>> + 'a + Send>> {
-        let span = self.create_span(&stmt);
+        let span = self.create_span(&stmt, None);
         let start = Instant::now();
         let config = self.config.clone();
 
@@ -295,22 +396,7 @@ impl StreamTrait for TracedConnection {
             let result = self.inner.stream(stmt).instrument(span.clone()).await;
 
             // Record basic result info (we can't know row count for streams)
-            let duration_ms = start.elapsed().as_millis() as i64;
-            span.record("db.duration_ms", duration_ms);
-
-            if start.elapsed() > config.slow_query_threshold {
-                span.record("slow_query", true);
-            }
-
-            match &result {
-                Ok(_) => {
-                    span.record("otel.status_code", "OK");
-                }
-                Err(e) => {
-                    span.record("otel.status_code", "ERROR");
-                    span.record("error.message", e.to_string().as_str());
-                }
-            }
+            instrument::record_stream_result(&config, &span, &result, start);
 
             result
         })
@@ -318,30 +404,17 @@ impl StreamTrait for TracedConnection {
 }
 
 #[async_trait]
-impl TransactionTrait for TracedConnection {
+impl<C: ConnectionTrait + TransactionTrait + Send> TransactionTrait for TracedConnection<C> {
     async fn begin(&self) -> Result<DatabaseTransaction, DbErr> {
-        let span = tracing::info_span!(
-            "db.transaction",
-            otel.name = "BEGIN",
-            db.system = %self.db_system(),
-            db.operation = "BEGIN",
-            otel.status_code = field::Empty,
-            error.message = field::Empty,
-        );
-
-        let result = self.inner.begin().instrument(span.clone()).await;
-
-        match &result {
-            Ok(_) => {
-                span.record("otel.status_code", "OK");
-            }
-            Err(e) => {
-                span.record("otel.status_code", "ERROR");
-                span.record("error.message", e.to_string().as_str());
-            }
-        }
-
-        result
+        transaction::begin_detached(
+            &self.inner,
+            &self.config,
+            &Span::current(),
+            self.begin_depth != Some(0),
+            None,
+            None,
+        )
+        .await
     }
 
     async fn begin_with_config(
@@ -349,34 +422,15 @@ impl TransactionTrait for TracedConnection {
         isolation_level: Option<IsolationLevel>,
         access_mode: Option<AccessMode>,
     ) -> Result<DatabaseTransaction, DbErr> {
-        let span = tracing::info_span!(
-            "db.transaction",
-            otel.name = "BEGIN",
-            db.system = %self.db_system(),
-            db.operation = "BEGIN",
-            db.transaction.isolation_level = ?isolation_level,
-            db.transaction.access_mode = ?access_mode,
-            otel.status_code = field::Empty,
-            error.message = field::Empty,
-        );
-
-        let result = self
-            .inner
-            .begin_with_config(isolation_level, access_mode)
-            .instrument(span.clone())
-            .await;
-
-        match &result {
-            Ok(_) => {
-                span.record("otel.status_code", "OK");
-            }
-            Err(e) => {
-                span.record("otel.status_code", "ERROR");
-                span.record("error.message", e.to_string().as_str());
-            }
-        }
-
-        result
+        transaction::begin_detached(
+            &self.inner,
+            &self.config,
+            &Span::current(),
+            self.begin_depth != Some(0),
+            isolation_level,
+            access_mode,
+        )
+        .await
     }
 
     async fn transaction<F, T, E>(&self, callback: F) -> Result<T, TransactionError<E>>
@@ -388,14 +442,8 @@ impl TransactionTrait for TracedConnection {
         T: Send,
         E: std::fmt::Display + std::fmt::Debug + Send,
     {
-        let span = tracing::info_span!(
-            "db.transaction",
-            otel.name = "TRANSACTION",
-            db.system = %self.db_system(),
-            db.operation = "TRANSACTION",
-            otel.status_code = field::Empty,
-            error.message = field::Empty,
-        );
+        let span =
+            transaction::transaction_span(&self.config, self.get_database_backend(), "TRANSACTION");
 
         let result = self
             .inner
@@ -403,15 +451,7 @@ impl TransactionTrait for TracedConnection {
             .instrument(span.clone())
             .await;
 
-        match &result {
-            Ok(_) => {
-                span.record("otel.status_code", "OK");
-            }
-            Err(e) => {
-                span.record("otel.status_code", "ERROR");
-                span.record("error.message", format!("{:?}", e).as_str());
-            }
-        }
+        transaction::record_closure_result(&span, &result);
 
         result
     }
@@ -430,16 +470,9 @@ impl TransactionTrait for TracedConnection {
         T: Send,
         E: std::fmt::Display + std::fmt::Debug + Send,
     {
-        let span = tracing::info_span!(
-            "db.transaction",
-            otel.name = "TRANSACTION",
-            db.system = %self.db_system(),
-            db.operation = "TRANSACTION",
-            db.transaction.isolation_level = ?isolation_level,
-            db.transaction.access_mode = ?access_mode,
-            otel.status_code = field::Empty,
-            error.message = field::Empty,
-        );
+        let span =
+            transaction::transaction_span(&self.config, self.get_database_backend(), "TRANSACTION");
+        transaction::record_transaction_config(&span, isolation_level, access_mode);
 
         let result = self
             .inner
@@ -447,42 +480,72 @@ impl TransactionTrait for TracedConnection {
             .instrument(span.clone())
             .await;
 
-        match &result {
-            Ok(_) => {
-                span.record("otel.status_code", "OK");
-            }
-            Err(e) => {
-                span.record("otel.status_code", "ERROR");
-                span.record("error.message", format!("{:?}", e).as_str());
-            }
-        }
+        transaction::record_closure_result(&span, &result);
 
         result
     }
 }
 
 /// Extension trait for easy wrapping of database connections.
-pub trait TracingExt {
+///
+/// Implemented for `DatabaseConnection` (including `MockDatabase` connections) and
+/// `DatabaseTransaction`. Wrap a custom `ConnectionTrait` implementation with
+/// [`TracedConnection::new`] instead. A `TracedConnection` is not wrapped again, since
+/// that would trace every statement twice.
+pub trait TracingExt: Sized {
     /// Wrap this connection with tracing instrumentation.
-    fn with_tracing(self) -> TracedConnection;
+    fn with_tracing(self) -> TracedConnection<Self>;
 
     /// Wrap this connection with custom tracing configuration.
-    fn with_tracing_config(self, config: TracingConfig) -> TracedConnection;
+    fn with_tracing_config(self, config: TracingConfig) -> TracedConnection<Self>;
 }
 
 impl TracingExt for DatabaseConnection {
-    fn with_tracing(self) -> TracedConnection {
+    fn with_tracing(self) -> TracedConnection<Self> {
         TracedConnection::wrap(self)
     }
 
-    fn with_tracing_config(self, config: TracingConfig) -> TracedConnection {
+    fn with_tracing_config(self, config: TracingConfig) -> TracedConnection<Self> {
         TracedConnection::new(self, config)
     }
 }
 
+/// The depth of the wrapped transaction is unknown; use
+/// [`TracedConnection::from_transaction`] to declare it.
+impl TracingExt for DatabaseTransaction {
+    fn with_tracing(self) -> TracedConnection<Self> {
+        TracedConnection::nested(self, TracingConfig::default())
+    }
+
+    fn with_tracing_config(self, config: TracingConfig) -> TracedConnection<Self> {
+        TracedConnection::nested(self, config)
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use std::collections::BTreeMap;
+
+    use sea_orm::{MockDatabase, MockExecResult, Value};
+
     use super::*;
+    use crate::test_support::Capture;
+    use crate::SemconvVersion;
+
+    fn mock_connection() -> DatabaseConnection {
+        MockDatabase::new(DbBackend::Postgres)
+            .append_exec_results([
+                MockExecResult {
+                    last_insert_id: 0,
+                    rows_affected: 1,
+                },
+                MockExecResult {
+                    last_insert_id: 0,
+                    rows_affected: 2,
+                },
+            ])
+            .into_connection()
+    }
 
     #[test]
     fn test_config_builder() {
@@ -506,5 +569,267 @@ mod tests {
         let config = TracingConfig::production();
         assert!(!config.log_statements);
         assert!(!config.log_parameters);
+        assert_eq!(config.statement_mode, crate::StatementMode::Sanitized);
+    }
+
+    #[tokio::test]
+    async fn test_wrap_mock_connection() {
+        let traced = mock_connection().with_tracing();
+
+        let result = traced
+            .execute_unprepared("DELETE FROM sessions")
+            .await
+            .unwrap();
+        assert_eq!(result.rows_affected(), 1);
+        assert!(traced.is_mock_connection());
+    }
+
+    #[tokio::test]
+    async fn test_last_insert_id() {
+        let (capture, _guard) = Capture::install();
+        let insert = "INSERT INTO cakes (name) VALUES ('lemon')";
+
+        for backend in [DbBackend::MySql, DbBackend::Postgres] {
+            let traced = MockDatabase::new(backend)
+                .append_exec_results([MockExecResult {
+                    last_insert_id: 42,
+                    rows_affected: 1,
+                }])
+                .into_connection()
+                .with_tracing();
+            traced.execute_unprepared(insert).await.unwrap();
+        }
+
+        let spans = capture.spans("db.query");
+        assert_eq!(spans[0].get("db.last_insert_id"), Some("42"));
+        // Postgres has no last insert id and the span is left without one
+        assert_eq!(spans[1].get("db.system"), Some("postgresql"));
+        assert_eq!(spans[1].get("db.last_insert_id"), None);
+    }
+
+    #[tokio::test]
+    async fn test_stable_row_counts() {
+        let (capture, _guard) = Capture::install();
+        let traced = MockDatabase::new(DbBackend::Postgres)
+            .append_query_results([[BTreeMap::from([("id", Value::from(1))])]])
+            .append_exec_results([MockExecResult {
+                last_insert_id: 0,
+                rows_affected: 3,
+            }])
+            .into_connection()
+            .with_tracing_config(
+                TracingConfig::default().with_semconv_version(SemconvVersion::Stable),
+            );
+
+        traced
+            .query_all(Statement::from_string(
+                DbBackend::Postgres,
+                "SELECT id FROM users",
+            ))
+            .await
+            .unwrap();
+        traced
+            .execute_unprepared("DELETE FROM sessions")
+            .await
+            .unwrap();
+
+        let spans = capture.spans("db.query");
+        let (select, delete) = (&spans[0], &spans[1]);
+        assert_eq!(select.get("db.response.returned_rows"), Some("1"));
+        assert_eq!(select.get("db.rows_affected"), None);
+        // Writes return no rows; their count keeps its own attribute
+        assert_eq!(delete.get("db.response.returned_rows"), None);
+        assert_eq!(delete.get("db.rows_affected"), Some("3"));
+    }
+
+    #[tokio::test]
+    async fn test_parse_cache_shared_with_transactions() {
+        let traced = mock_connection().with_tracing();
+        traced
+            .execute_unprepared("DELETE FROM sessions")
+            .await
+            .unwrap();
+
+        let txn = traced.begin().await.unwrap();
+        txn.execute_unprepared("DELETE FROM sessions")
+            .await
+            .unwrap();
+        txn.commit().await.unwrap();
+
+        // BEGIN and COMMIT are not parsed, and DELETE is parsed once
+        let stats = traced.parse_cache_stats();
+        assert_eq!((stats.hits, stats.misses, stats.entries), (1, 1, 1));
+    }
+
+    #[tokio::test]
+    async fn test_wrap_database_transaction() {
+        let db = mock_connection();
+        let txn = TransactionTrait::begin(&db).await.unwrap();
+        let traced: TracedConnection<DatabaseTransaction> = TracedConnection::from(txn);
+
+        let result = traced
+            .execute_unprepared("UPDATE users SET active = false")
+            .await
+            .unwrap();
+        assert_eq!(result.rows_affected(), 1);
+
+        traced.into_inner().commit().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_begin_on_wrapped_transaction() {
+        let (capture, _guard) = Capture::install();
+        let db = mock_connection();
+        let txn = TransactionTrait::begin(&db).await.unwrap();
+        let traced = TracedConnection::from_transaction(txn, 0);
+
+        let nested = traced.begin().await.unwrap();
+        assert_eq!(nested.depth(), Some(1));
+        nested.commit().await.unwrap();
+        traced.into_inner().commit().await.unwrap();
+
+        let spans = capture.spans("db.transaction");
+        let nested = spans.last().unwrap();
+        assert_eq!(nested.get("db.transaction.depth"), Some("1"));
+        assert_eq!(nested.get("db.transaction.outcome"), Some("committed"));
+        // The driver of a wrapped transaction is unknown, and so is its savepoint name
+        assert_eq!(nested.get("db.transaction.savepoint"), None);
+
+        // The nested transaction is opened and released with a savepoint
+        let operations: Vec<_> = capture
+            .spans("db.query")
+            .iter()
+            .filter_map(|span| span.get("db.operation").map(str::to_string))
+            .collect();
+        assert_eq!(operations, ["SAVEPOINT", "RELEASE SAVEPOINT"]);
+    }
+
+    #[tokio::test]
+    async fn test_begin_on_deeper_wrapped_transaction() {
+        let (capture, _guard) = Capture::install();
+        let db = mock_connection();
+        let outer = TransactionTrait::begin(&db).await.unwrap();
+        let inner = TransactionTrait::begin(&outer).await.unwrap();
+        let traced = TracedConnection::from_transaction(inner, 1);
+
+        let nested = traced.begin().await.unwrap();
+        assert_eq!(nested.depth(), Some(2));
+        nested.commit().await.unwrap();
+        traced.into_inner().commit().await.unwrap();
+        outer.commit().await.unwrap();
+
+        let spans = capture.spans("db.transaction");
+        assert_eq!(spans.len(), 1);
+        assert_eq!(spans[0].get("db.transaction.depth"), Some("2"));
+        assert_eq!(spans[0].get("db.transaction.savepoint"), None);
+
+        let spans = capture.spans("db.query");
+        let operations: Vec<_> = spans
+            .iter()
+            .filter_map(|span| span.get("db.operation").map(str::to_string))
+            .collect();
+        assert_eq!(operations, ["SAVEPOINT", "RELEASE SAVEPOINT"]);
+        assert!(spans
+            .iter()
+            .all(|span| span.get("db.transaction.savepoint").is_none()));
+    }
+
+    #[tokio::test]
+    async fn test_begin_on_transaction_of_unknown_depth() {
+        let (capture, _guard) = Capture::install();
+        let db = mock_connection();
+        let txn = TransactionTrait::begin(&db).await.unwrap();
+        let traced = txn.with_tracing();
+
+        let nested = traced.begin().await.unwrap();
+        assert_eq!(nested.depth(), None);
+        nested.rollback().await.unwrap();
+        traced.into_inner().commit().await.unwrap();
+
+        // Traced as a savepoint, without claiming a depth
+        let spans = capture.spans("db.transaction");
+        assert_eq!(spans[0].get("db.transaction.depth"), None);
+        assert_eq!(spans[0].get("db.transaction.outcome"), Some("rolled_back"));
+
+        let operations: Vec<_> = capture
+            .spans("db.query")
+            .iter()
+            .filter_map(|span| span.get("db.operation").map(str::to_string))
+            .collect();
+        assert_eq!(operations, ["SAVEPOINT", "ROLLBACK TO SAVEPOINT"]);
+    }
+
+    #[tokio::test]
+    async fn test_raw_begin_traced_as_control_span() {
+        let (capture, _guard) = Capture::install();
+        let traced = mock_connection().with_tracing();
+
+        let txn = TransactionTrait::begin(&traced).await.unwrap();
+        txn.commit().await.unwrap();
+        let txn = traced.begin().await.unwrap();
+        let nested = TransactionTrait::begin_with_config(&txn, None, Some(AccessMode::ReadOnly))
+            .await
+            .unwrap();
+        nested.commit().await.unwrap();
+        txn.commit().await.unwrap();
+
+        // The raw transaction is not tracked, so only its BEGIN is traced
+        let spans = capture.spans("db.transaction");
+        assert_eq!(spans.len(), 1);
+        assert_eq!(spans[0].get("db.transaction.depth"), Some("0"));
+
+        let spans = capture.spans("db.query");
+        let operations: Vec<_> = spans.iter().map(|span| span.get("db.operation")).collect();
+        assert_eq!(
+            operations,
+            [
+                Some("BEGIN"),
+                Some("BEGIN"),
+                Some("SAVEPOINT"),
+                Some("COMMIT")
+            ]
+        );
+        assert_eq!(spans[2].get("db.transaction.access_mode"), Some("ReadOnly"));
+    }
+
+    #[tokio::test]
+    async fn test_generic_transaction_trait() {
+        async fn transfer<C: TransactionTrait>(
+            db: &C,
+            fail: bool,
+        ) -> Result<(), TransactionError<DbErr>> {
+            db.transaction::<_, _, DbErr>(|txn| {
+                Box::pin(async move {
+                    txn.execute_unprepared("UPDATE accounts SET balance = 0")
+                        .await?;
+                    if fail {
+                        return Err(DbErr::Custom("insufficient funds".into()));
+                    }
+                    Ok(())
+                })
+            })
+            .await
+        }
+
+        let (capture, _guard) = Capture::install();
+        let traced = mock_connection().with_tracing();
+
+        transfer(&traced, false).await.unwrap();
+        transfer(&traced, true).await.unwrap_err();
+
+        let outcomes: Vec<_> = capture
+            .spans("db.transaction")
+            .iter()
+            .map(|span| span.get("db.transaction.outcome").map(str::to_string))
+            .collect();
+        assert_eq!(
+            outcomes,
+            [
+                Some("committed".to_string()),
+                Some("rolled_back".to_string())
+            ]
+        );
+        // The closure receives a raw DatabaseTransaction, so its statements are not traced
+        assert!(capture.spans("db.query").is_empty());
     }
 }
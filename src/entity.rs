@@ -0,0 +1,457 @@
+//! Entity-aware query helpers.
+//!
+//! `ConnectionTrait` only sees rendered statements, so the spans created by
+//! [`TracedConnection`](crate::TracedConnection) cannot say which entity a query was built
+//! from. The extension traits in this module run SeaORM queries through a connection
+//! that attaches the entity name, the selected columns and whether a LIMIT is present
+//! to the span of the resulting statement.
+
+use async_trait::async_trait;
+use sea_orm::sea_query::{ColumnRef, DynIden, Iden, SelectExpr, SelectStatement, SimpleExpr};
+use sea_orm::{
+    ActiveModelTrait, ConnectionTrait, DbBackend, DbErr, DeleteMany, DeleteResult, EntityTrait,
+    ExecResult, Insert, InsertResult, QueryResult, QueryTrait, Select, SelectTwo, Statement,
+    UpdateMany, UpdateResult,
+};
+
+use crate::connection::TracedConnection;
+use crate::transaction::TracedTransaction;
+
+/// SeaORM metadata recorded on the span of a statement.
+#[derive(Debug, Clone, Default)]
+pub struct EntityInfo {
+    /// Names of the entities the query was built from.
+    pub(crate) entities: Vec<String>,
+    /// Columns selected by the query, as `table.column` where the table is known.
+    pub(crate) columns: Vec<String>,
+    /// Whether the statement reads rows, so LIMIT presence is meaningful.
+    pub(crate) is_select: bool,
+}
+
+impl EntityInfo {
+    fn select(entities: Vec<String>, query: &mut SelectStatement) -> Self {
+        let mut columns = Vec::new();
+        query.exprs_mut_for_each(|select| columns.extend(column_name(select)));
+        Self {
+            entities,
+            columns,
+            is_select: true,
+        }
+    }
+
+    fn write(entity: String) -> Self {
+        Self {
+            entities: vec![entity],
+            ..Self::default()
+        }
+    }
+}
+
+/// The name of an entity: its table name, qualified with the schema when it has one.
+fn entity_name<E: EntityTrait>() -> String {
+    let entity = E::default();
+    match entity.schema_name() {
+        Some(schema) => format!("{}.{}", schema, entity.table_name()),
+        None => entity.table_name().to_string(),
+    }
+}
+
+/// The name of a selected column, or its alias for other expressions.
+fn column_name(select: &SelectExpr) -> Option<String> {
+    match &select.expr {
+        SimpleExpr::Column(ColumnRef::Column(column)) => Some(iden(column)),
+        SimpleExpr::Column(
+            ColumnRef::TableColumn(table, column) | ColumnRef::SchemaTableColumn(_, table, column),
+        ) => Some(format!("{}.{}", iden(table), iden(column))),
+        SimpleExpr::Column(ColumnRef::Asterisk) => Some("*".to_string()),
+        SimpleExpr::Column(ColumnRef::TableAsterisk(table)) => Some(format!("{}.*", iden(table))),
+        _ => select.alias.as_ref().map(iden),
+    }
+}
+
+fn iden(iden: &DynIden) -> String {
+    Iden::to_string(&**iden)
+}
+
+mod private {
+    pub trait Sealed {}
+
+    impl<C> Sealed for crate::TracedConnection<C> {}
+    impl Sealed for crate::TracedTransaction {}
+}
+
+/// A traced connection that can record entity metadata on its spans.
+///
+/// Implemented by [`TracedConnection`] and [`TracedTransaction`], so the entity-aware
+/// helpers work both inside and outside transactions.
+#[async_trait]
+pub trait TracedConnectionTrait: ConnectionTrait + private::Sealed {
+    #[doc(hidden)]
+    async fn execute_with_entity(
+        &self,
+        stmt: Statement,
+        entity: &EntityInfo,
+    ) -> Result<ExecResult, DbErr>;
+
+    #[doc(hidden)]
+    async fn query_one_with_entity(
+        &self,
+        stmt: Statement,
+        entity: &EntityInfo,
+    ) -> Result<Option<QueryResult>, DbErr>;
+
+    #[doc(hidden)]
+    async fn query_all_with_entity(
+        &self,
+        stmt: Statement,
+        entity: &EntityInfo,
+    ) -> Result<Vec<QueryResult>, DbErr>;
+}
+
+#[async_trait]
+impl<C: ConnectionTrait> TracedConnectionTrait for TracedConnection<C> {
+    async fn execute_with_entity(
+        &self,
+        stmt: Statement,
+        entity: &EntityInfo,
+    ) -> Result<ExecResult, DbErr> {
+        self.execute_traced(stmt, Some(entity)).await
+    }
+
+    async fn query_one_with_entity(
+        &self,
+        stmt: Statement,
+        entity: &EntityInfo,
+    ) -> Result<Option<QueryResult>, DbErr> {
+        self.query_one_traced(stmt, Some(entity)).await
+    }
+
+    async fn query_all_with_entity(
+        &self,
+        stmt: Statement,
+        entity: &EntityInfo,
+    ) -> Result<Vec<QueryResult>, DbErr> {
+        self.query_all_traced(stmt, Some(entity)).await
+    }
+}
+
+#[async_trait]
+impl TracedConnectionTrait for TracedTransaction {
+    async fn execute_with_entity(
+        &self,
+        stmt: Statement,
+        entity: &EntityInfo,
+    ) -> Result<ExecResult, DbErr> {
+        self.execute_traced(stmt, Some(entity)).await
+    }
+
+    async fn query_one_with_entity(
+        &self,
+        stmt: Statement,
+        entity: &EntityInfo,
+    ) -> Result<Option<QueryResult>, DbErr> {
+        self.query_one_traced(stmt, Some(entity)).await
+    }
+
+    async fn query_all_with_entity(
+        &self,
+        stmt: Statement,
+        entity: &EntityInfo,
+    ) -> Result<Vec<QueryResult>, DbErr> {
+        self.query_all_traced(stmt, Some(entity)).await
+    }
+}
+
+/// A connection that records `entity` on the span of every statement run through it.
+struct WithEntity<'a, C> {
+    conn: &'a C,
+    entity: EntityInfo,
+}
+
+#[async_trait]
+impl<C: TracedConnectionTrait> ConnectionTrait for WithEntity<'_, C> {
+    fn get_database_backend(&self) -> DbBackend {
+        self.conn.get_database_backend()
+    }
+
+    async fn execute(&self, stmt: Statement) -> Result<ExecResult, DbErr> {
+        self.conn.execute_with_entity(stmt, &self.entity).await
+    }
+
+    async fn execute_unprepared(&self, sql: &str) -> Result<ExecResult, DbErr> {
+        self.conn.execute_unprepared(sql).await
+    }
+
+    async fn query_one(&self, stmt: Statement) -> Result<Option<QueryResult>, DbErr> {
+        self.conn.query_one_with_entity(stmt, &self.entity).await
+    }
+
+    async fn query_all(&self, stmt: Statement) -> Result<Vec<QueryResult>, DbErr> {
+        self.conn.query_all_with_entity(stmt, &self.entity).await
+    }
+
+    fn support_returning(&self) -> bool {
+        self.conn.support_returning()
+    }
+
+    fn is_mock_connection(&self) -> bool {
+        self.conn.is_mock_connection()
+    }
+}
+
+/// Run SeaORM selects with the entity name, selected columns and LIMIT presence
+/// recorded on the query span.
+///
+/// Implemented for `Select<E>` and `SelectTwo<E, F>`.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// use sea_orm_tracing::TracedSelectExt;
+///
+/// // Span gets db.sea_orm.entity = "cake" and db.sea_orm.has_limit = true
+/// let cake = Cake::find_by_id(1).traced_one(&traced_db).await?;
+/// let cakes = Cake::find().traced_all(&traced_db).await?;
+/// ```
+#[async_trait]
+pub trait TracedSelectExt: Sized {
+    /// The item each row is converted to.
+    type Item;
+
+    /// Like `all`, recording entity metadata on the span.
+    async fn traced_all<C>(self, db: &C) -> Result<Vec<Self::Item>, DbErr>
+    where
+        C: TracedConnectionTrait;
+
+    /// Like `one`, recording entity metadata on the span.
+    async fn traced_one<C>(self, db: &C) -> Result<Option<Self::Item>, DbErr>
+    where
+        C: TracedConnectionTrait;
+}
+
+#[async_trait]
+impl<E: EntityTrait> TracedSelectExt for Select<E> {
+    type Item = E::Model;
+
+    async fn traced_all<C>(mut self, db: &C) -> Result<Vec<E::Model>, DbErr>
+    where
+        C: TracedConnectionTrait,
+    {
+        let entity = EntityInfo::select(vec![entity_name::<E>()], self.query());
+        self.all(&WithEntity { conn: db, entity }).await
+    }
+
+    async fn traced_one<C>(mut self, db: &C) -> Result<Option<E::Model>, DbErr>
+    where
+        C: TracedConnectionTrait,
+    {
+        let entity = EntityInfo::select(vec![entity_name::<E>()], self.query());
+        self.one(&WithEntity { conn: db, entity }).await
+    }
+}
+
+#[async_trait]
+impl<E: EntityTrait, F: EntityTrait> TracedSelectExt for SelectTwo<E, F> {
+    type Item = (E::Model, Option<F::Model>);
+
+    async fn traced_all<C>(mut self, db: &C) -> Result<Vec<Self::Item>, DbErr>
+    where
+        C: TracedConnectionTrait,
+    {
+        let entities = vec![entity_name::<E>(), entity_name::<F>()];
+        let entity = EntityInfo::select(entities, self.query());
+        self.all(&WithEntity { conn: db, entity }).await
+    }
+
+    async fn traced_one<C>(mut self, db: &C) -> Result<Option<Self::Item>, DbErr>
+    where
+        C: TracedConnectionTrait,
+    {
+        let entities = vec![entity_name::<E>(), entity_name::<F>()];
+        let entity = EntityInfo::select(entities, self.query());
+        self.one(&WithEntity { conn: db, entity }).await
+    }
+}
+
+/// Run SeaORM inserts, updates and deletes with the entity name recorded on the query
+/// span.
+///
+/// Implemented for `Insert<A>`, `UpdateMany<E>` and `DeleteMany<E>`.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// use sea_orm_tracing::TracedExecExt;
+///
+/// let res = Cake::insert(cake).traced_exec(&traced_db).await?;
+/// Cake::delete_many().filter(cake::Column::Stale.eq(true)).traced_exec(&traced_db).await?;
+/// ```
+#[async_trait]
+pub trait TracedExecExt: Sized {
+    /// The result of executing the statement.
+    type Output;
+
+    /// Like `exec`, recording entity metadata on the span.
+    async fn traced_exec<C>(self, db: &C) -> Result<Self::Output, DbErr>
+    where
+        C: TracedConnectionTrait;
+}
+
+#[async_trait]
+impl<A: ActiveModelTrait + Send> TracedExecExt for Insert<A> {
+    type Output = InsertResult<A>;
+
+    async fn traced_exec<C>(self, db: &C) -> Result<InsertResult<A>, DbErr>
+    where
+        C: TracedConnectionTrait,
+    {
+        let entity = EntityInfo::write(entity_name::<A::Entity>());
+        self.exec(&WithEntity { conn: db, entity }).await
+    }
+}
+
+#[async_trait]
+impl<E: EntityTrait> TracedExecExt for UpdateMany<E> {
+    type Output = UpdateResult;
+
+    async fn traced_exec<C>(self, db: &C) -> Result<UpdateResult, DbErr>
+    where
+        C: TracedConnectionTrait,
+    {
+        let entity = EntityInfo::write(entity_name::<E>());
+        self.exec(&WithEntity { conn: db, entity }).await
+    }
+}
+
+#[async_trait]
+impl<E: EntityTrait> TracedExecExt for DeleteMany<E> {
+    type Output = DeleteResult;
+
+    async fn traced_exec<C>(self, db: &C) -> Result<DeleteResult, DbErr>
+    where
+        C: TracedConnectionTrait,
+    {
+        let entity = EntityInfo::write(entity_name::<E>());
+        self.exec(&WithEntity { conn: db, entity }).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sea_orm::entity::prelude::*;
+    use sea_orm::{MockDatabase, MockExecResult};
+
+    use super::*;
+    use crate::test_support::Capture;
+    use crate::TracingExt;
+
+    mod cake {
+        use sea_orm::entity::prelude::*;
+
+        #[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+        #[sea_orm(table_name = "cake")]
+        pub struct Model {
+            #[sea_orm(primary_key)]
+            pub id: i32,
+            pub name: String,
+        }
+
+        #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+        pub enum Relation {}
+
+        impl ActiveModelBehavior for ActiveModel {}
+    }
+
+    mod fruit {
+        use sea_orm::entity::prelude::*;
+
+        #[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+        #[sea_orm(table_name = "fruit_basket", schema_name = "shop")]
+        pub struct Model {
+            #[sea_orm(primary_key)]
+            pub id: i32,
+        }
+
+        #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+        pub enum Relation {}
+
+        impl ActiveModelBehavior for ActiveModel {}
+    }
+
+    #[test]
+    fn test_entity_metadata() {
+        assert_eq!(entity_name::<cake::Entity>(), "cake");
+        // The table name is used, not the module the entity is defined in
+        assert_eq!(entity_name::<fruit::Entity>(), "shop.fruit_basket");
+
+        let mut select = cake::Entity::find();
+        let query = QueryTrait::query(&mut select);
+        let info = EntityInfo::select(vec![entity_name::<cake::Entity>()], query);
+        assert_eq!(info.columns, ["cake.id", "cake.name"]);
+        assert!(info.is_select);
+    }
+
+    #[tokio::test]
+    async fn test_traced_helpers() {
+        let model = cake::Model {
+            id: 1,
+            name: "lemon".to_string(),
+        };
+        let db = MockDatabase::new(DbBackend::Postgres)
+            .append_query_results([vec![model.clone()], vec![model.clone()]])
+            .append_exec_results([MockExecResult {
+                last_insert_id: 0,
+                rows_affected: 3,
+            }])
+            .into_connection()
+            .with_tracing();
+
+        let all = cake::Entity::find().traced_all(&db).await.unwrap();
+        let one = cake::Entity::find_by_id(1).traced_one(&db).await.unwrap();
+        assert_eq!(all, vec![model.clone()]);
+        assert_eq!(one, Some(model));
+
+        let deleted = cake::Entity::delete_many()
+            .filter(cake::Column::Name.eq("stale"))
+            .traced_exec(&db)
+            .await
+            .unwrap();
+        assert_eq!(deleted.rows_affected, 3);
+    }
+
+    #[tokio::test]
+    async fn test_entity_recorded_on_span() {
+        let (capture, _guard) = Capture::install();
+        let model = cake::Model {
+            id: 1,
+            name: "lemon".to_string(),
+        };
+        let db = MockDatabase::new(DbBackend::Postgres)
+            .append_query_results([vec![model.clone()], vec![model]])
+            .append_exec_results([MockExecResult {
+                last_insert_id: 0,
+                rows_affected: 1,
+            }])
+            .into_connection()
+            .with_tracing();
+
+        cake::Entity::find().traced_all(&db).await.unwrap();
+        cake::Entity::find().traced_one(&db).await.unwrap();
+        cake::Entity::delete_many().traced_exec(&db).await.unwrap();
+
+        let spans = capture.spans("db.query");
+        let (all, one, delete) = (&spans[0], &spans[1], &spans[2]);
+        assert_eq!(all.get("db.sea_orm.has_limit"), Some("false"));
+        assert_eq!(one.get("db.sea_orm.has_limit"), Some("true"));
+        assert_eq!(delete.get("db.sea_orm.has_limit"), None);
+        // List attributes are exported as arrays with the `opentelemetry` feature
+        #[cfg(not(feature = "opentelemetry"))]
+        {
+            assert_eq!(all.get("db.sea_orm.entity"), Some("cake"));
+            assert_eq!(all.get("db.sea_orm.columns"), Some("cake.id,cake.name"));
+            // Writes record the entity only
+            assert_eq!(delete.get("db.sea_orm.entity"), Some("cake"));
+            assert_eq!(delete.get("db.sea_orm.columns"), None);
+        }
+    }
+}
@@ -0,0 +1,429 @@
+//! Classification of database errors.
+
+use sea_orm::{ConnAcquireErr, DbErr, SqlErr};
+
+#[cfg(any(
+    feature = "sqlx-mysql",
+    feature = "sqlx-postgres",
+    feature = "sqlx-sqlite"
+))]
+use sea_orm::sqlx;
+#[cfg(any(
+    test,
+    feature = "sqlx-mysql",
+    feature = "sqlx-postgres",
+    feature = "sqlx-sqlite"
+))]
+use sea_orm::RuntimeErr;
+
+/// Category of a database error, recorded as `error.type` along with `error.retryable`.
+///
+/// The class comes from the [`DbErr`] variant and, when a `sqlx-*` feature is enabled,
+/// from the code the database reported. Retry logic can share the classification:
+///
+/// ```rust
+/// use sea_orm::DbErr;
+/// use sea_orm_tracing::ErrorClass;
+///
+/// fn should_retry(err: &DbErr) -> bool {
+///     ErrorClass::from_db_err(err).is_retryable()
+/// }
+///
+/// assert!(!should_retry(&DbErr::RecordNotFound("cake".into())));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorClass {
+    /// The connection failed or was lost.
+    Connection,
+    /// No connection became available in the pool in time.
+    PoolTimeout,
+    /// A record expected to exist was not found.
+    NotFound,
+    /// A unique or primary key constraint was violated.
+    UniqueViolation,
+    /// A foreign key constraint was violated.
+    ForeignKeyViolation,
+    /// The transaction conflicted with a concurrent one and was aborted.
+    SerializationFailure,
+    /// The statement deadlocked or timed out waiting for a lock.
+    Deadlock,
+    /// The statement is not valid SQL.
+    Syntax,
+    /// The user may not perform the statement.
+    Permission,
+    /// An application error raised through [`DbErr::Custom`].
+    Custom,
+    /// An error returned by a transaction closure rather than by the database.
+    Application,
+    /// Any other error.
+    Other,
+}
+
+impl ErrorClass {
+    /// Classify a database error.
+    pub fn from_db_err(err: &DbErr) -> Self {
+        match err {
+            DbErr::ConnectionAcquire(ConnAcquireErr::Timeout) => return ErrorClass::PoolTimeout,
+            DbErr::ConnectionAcquire(ConnAcquireErr::ConnectionClosed) => {
+                return ErrorClass::Connection
+            }
+            DbErr::RecordNotFound(_) => return ErrorClass::NotFound,
+            DbErr::Custom(_) => return ErrorClass::Custom,
+            _ => {}
+        }
+
+        #[cfg(any(
+            feature = "sqlx-mysql",
+            feature = "sqlx-postgres",
+            feature = "sqlx-sqlite"
+        ))]
+        if let Some(class) = sqlx_error(err).and_then(classify_sqlx) {
+            return class;
+        }
+
+        // SeaORM recognizes constraint violations when it is built with a driver
+        match err.sql_err() {
+            Some(SqlErr::UniqueConstraintViolation(_)) => ErrorClass::UniqueViolation,
+            Some(SqlErr::ForeignKeyConstraintViolation(_)) => ErrorClass::ForeignKeyViolation,
+            _ if matches!(err, DbErr::Conn(_)) => ErrorClass::Connection,
+            _ => ErrorClass::Other,
+        }
+    }
+
+    /// The `error.type` value.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorClass::Connection => "connection",
+            ErrorClass::PoolTimeout => "pool_timeout",
+            ErrorClass::NotFound => "not_found",
+            ErrorClass::UniqueViolation => "unique_violation",
+            ErrorClass::ForeignKeyViolation => "foreign_key_violation",
+            ErrorClass::SerializationFailure => "serialization_failure",
+            ErrorClass::Deadlock => "deadlock",
+            ErrorClass::Syntax => "syntax",
+            ErrorClass::Permission => "permission",
+            ErrorClass::Custom => "custom",
+            ErrorClass::Application => "application",
+            ErrorClass::Other => "other",
+        }
+    }
+
+    /// Returns `true` if running the operation again may succeed.
+    ///
+    /// Connection failures, pool timeouts, serialization failures and deadlocks are
+    /// transient; retry serialization failures and deadlocks by running the whole
+    /// transaction again.
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            ErrorClass::Connection
+                | ErrorClass::PoolTimeout
+                | ErrorClass::SerializationFailure
+                | ErrorClass::Deadlock
+        )
+    }
+}
+
+impl From<&DbErr> for ErrorClass {
+    fn from(err: &DbErr) -> Self {
+        ErrorClass::from_db_err(err)
+    }
+}
+
+/// Classify an error reported by sqlx.
+#[cfg(any(
+    feature = "sqlx-mysql",
+    feature = "sqlx-postgres",
+    feature = "sqlx-sqlite"
+))]
+fn classify_sqlx(err: &sqlx::Error) -> Option<ErrorClass> {
+    use sqlx::error::ErrorKind;
+
+    let database_error = match err {
+        sqlx::Error::PoolTimedOut => return Some(ErrorClass::PoolTimeout),
+        sqlx::Error::Io(_)
+        | sqlx::Error::Tls(_)
+        | sqlx::Error::PoolClosed
+        | sqlx::Error::WorkerCrashed => return Some(ErrorClass::Connection),
+        sqlx::Error::RowNotFound => return Some(ErrorClass::NotFound),
+        sqlx::Error::Database(e) => e.as_ref(),
+        _ => return None,
+    };
+
+    #[cfg(feature = "sqlx-postgres")]
+    if let Some(pg) = database_error.try_downcast_ref::<sqlx::postgres::PgDatabaseError>() {
+        return postgres_class(pg.code());
+    }
+    #[cfg(feature = "sqlx-mysql")]
+    if let Some(mysql) = database_error.try_downcast_ref::<sqlx::mysql::MySqlDatabaseError>() {
+        return mysql_class(mysql.number());
+    }
+    #[cfg(feature = "sqlx-sqlite")]
+    if database_error
+        .try_downcast_ref::<sqlx::sqlite::SqliteError>()
+        .is_some()
+    {
+        let code = database_error.code()?.parse().ok()?;
+        return sqlite_class(code);
+    }
+
+    match database_error.kind() {
+        ErrorKind::UniqueViolation => Some(ErrorClass::UniqueViolation),
+        ErrorKind::ForeignKeyViolation => Some(ErrorClass::ForeignKeyViolation),
+        _ => None,
+    }
+}
+
+/// Classify a Postgres SQLSTATE.
+#[cfg(feature = "sqlx-postgres")]
+fn postgres_class(sqlstate: &str) -> Option<ErrorClass> {
+    Some(match sqlstate {
+        "23505" => ErrorClass::UniqueViolation,
+        "23503" => ErrorClass::ForeignKeyViolation,
+        "40001" => ErrorClass::SerializationFailure,
+        // deadlock_detected, lock_not_available
+        "40P01" | "55P03" => ErrorClass::Deadlock,
+        "42601" => ErrorClass::Syntax,
+        "42501" => ErrorClass::Permission,
+        // Class 08: connection exception, class 57P: operator intervention (shutdown)
+        code if code.starts_with("08") || code.starts_with("57P") => ErrorClass::Connection,
+        // Class 28: invalid authorization specification
+        code if code.starts_with("28") => ErrorClass::Permission,
+        _ => return None,
+    })
+}
+
+/// Classify a MySQL error number.
+#[cfg(feature = "sqlx-mysql")]
+fn mysql_class(number: u16) -> Option<ErrorClass> {
+    Some(match number {
+        // ER_DUP_KEY, ER_DUP_ENTRY, ER_DUP_ENTRY_WITH_KEY_NAME
+        1022 | 1062 | 1586 => ErrorClass::UniqueViolation,
+        // ER_NO_REFERENCED_ROW, ER_ROW_IS_REFERENCED and their _2 variants
+        1216 | 1217 | 1451 | 1452 => ErrorClass::ForeignKeyViolation,
+        // ER_LOCK_DEADLOCK, ER_LOCK_WAIT_TIMEOUT
+        1213 | 1205 => ErrorClass::Deadlock,
+        // ER_PARSE_ERROR, ER_SYNTAX_ERROR
+        1064 | 1149 => ErrorClass::Syntax,
+        // Access denied to a database, for a user, to a table, to a column, or to an action
+        1044 | 1045 | 1142 | 1143 | 1227 => ErrorClass::Permission,
+        // Server gone away, lost connection, too many connections
+        1040 | 2006 | 2013 => ErrorClass::Connection,
+        _ => return None,
+    })
+}
+
+/// Classify a SQLite extended result code.
+#[cfg(feature = "sqlx-sqlite")]
+fn sqlite_class(code: i32) -> Option<ErrorClass> {
+    Some(match code {
+        // SQLITE_CONSTRAINT_UNIQUE, SQLITE_CONSTRAINT_PRIMARYKEY
+        2067 | 1555 => ErrorClass::UniqueViolation,
+        // SQLITE_CONSTRAINT_FOREIGNKEY
+        787 => ErrorClass::ForeignKeyViolation,
+        // SQLITE_BUSY_SNAPSHOT: a read transaction cannot be upgraded to a write
+        517 => ErrorClass::SerializationFailure,
+        // SQLITE_BUSY and SQLITE_LOCKED, with their extended codes
+        code if matches!(code & 0xff, 5 | 6) => ErrorClass::Deadlock,
+        // SQLITE_PERM, SQLITE_AUTH
+        3 | 23 => ErrorClass::Permission,
+        _ => return None,
+    })
+}
+
+/// The code the database reported for an error, recorded as `db.response.status_code`.
+///
+/// This is the SQLSTATE on Postgres, the error number on MySQL (its SQLSTATE is coarse:
+/// `23000` covers every constraint violation) and the extended result code on SQLite,
+/// which has no SQLSTATE. Only errors raised by the database itself carry a code, and
+/// the code is only available when the matching `sqlx-*` feature is enabled.
+#[cfg(any(
+    feature = "sqlx-mysql",
+    feature = "sqlx-postgres",
+    feature = "sqlx-sqlite"
+))]
+pub(crate) fn status_code(err: &DbErr) -> Option<String> {
+    let database_error = database_error(err)?;
+    #[cfg(feature = "sqlx-mysql")]
+    if let Some(mysql) = database_error.try_downcast_ref::<sqlx::mysql::MySqlDatabaseError>() {
+        return Some(mysql.number().to_string());
+    }
+    database_error.code().map(|code| code.into_owned())
+}
+
+#[cfg(not(any(
+    feature = "sqlx-mysql",
+    feature = "sqlx-postgres",
+    feature = "sqlx-sqlite"
+)))]
+pub(crate) fn status_code(_err: &DbErr) -> Option<String> {
+    None
+}
+
+/// Unwrap the sqlx error behind `err`, if there is one.
+#[cfg(any(
+    feature = "sqlx-mysql",
+    feature = "sqlx-postgres",
+    feature = "sqlx-sqlite"
+))]
+fn sqlx_error(err: &DbErr) -> Option<&sqlx::Error> {
+    match err {
+        DbErr::Conn(RuntimeErr::SqlxError(e))
+        | DbErr::Exec(RuntimeErr::SqlxError(e))
+        | DbErr::Query(RuntimeErr::SqlxError(e)) => Some(e),
+        _ => None,
+    }
+}
+
+/// Unwrap the error reported by the database driver, if `err` is one.
+#[cfg(any(
+    feature = "sqlx-mysql",
+    feature = "sqlx-postgres",
+    feature = "sqlx-sqlite"
+))]
+fn database_error(err: &DbErr) -> Option<&dyn sqlx::error::DatabaseError> {
+    match sqlx_error(err)? {
+        sqlx::Error::Database(e) => Some(e.as_ref()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify() {
+        let class = ErrorClass::from_db_err;
+        assert_eq!(
+            class(&DbErr::ConnectionAcquire(ConnAcquireErr::Timeout)),
+            ErrorClass::PoolTimeout
+        );
+        assert_eq!(
+            class(&DbErr::Conn(RuntimeErr::Internal("refused".into()))),
+            ErrorClass::Connection
+        );
+        assert_eq!(
+            class(&DbErr::RecordNotFound("cake".into())),
+            ErrorClass::NotFound
+        );
+        assert_eq!(class(&DbErr::Custom("boom".into())), ErrorClass::Custom);
+        assert_eq!(class(&DbErr::Type("bad enum".into())), ErrorClass::Other);
+
+        assert!(ErrorClass::PoolTimeout.is_retryable());
+        assert!(ErrorClass::SerializationFailure.is_retryable());
+        assert!(!ErrorClass::UniqueViolation.is_retryable());
+        assert!(!ErrorClass::Custom.is_retryable());
+        assert!(!ErrorClass::Application.is_retryable());
+    }
+
+    #[cfg(any(
+        feature = "sqlx-mysql",
+        feature = "sqlx-postgres",
+        feature = "sqlx-sqlite"
+    ))]
+    #[test]
+    fn test_classify_sqlx() {
+        let pool = DbErr::Conn(RuntimeErr::SqlxError(sqlx::Error::PoolTimedOut));
+        assert_eq!(ErrorClass::from_db_err(&pool), ErrorClass::PoolTimeout);
+        let row = DbErr::Query(RuntimeErr::SqlxError(sqlx::Error::RowNotFound));
+        assert_eq!(ErrorClass::from_db_err(&row), ErrorClass::NotFound);
+    }
+
+    #[cfg(feature = "sqlx-postgres")]
+    #[test]
+    fn test_postgres_codes() {
+        assert_eq!(postgres_class("23505"), Some(ErrorClass::UniqueViolation));
+        assert_eq!(
+            postgres_class("40001"),
+            Some(ErrorClass::SerializationFailure)
+        );
+        assert_eq!(postgres_class("40P01"), Some(ErrorClass::Deadlock));
+        assert_eq!(postgres_class("08006"), Some(ErrorClass::Connection));
+        assert_eq!(postgres_class("28P01"), Some(ErrorClass::Permission));
+        assert_eq!(postgres_class("22012"), None);
+    }
+
+    #[cfg(feature = "sqlx-mysql")]
+    #[test]
+    fn test_mysql_codes() {
+        assert_eq!(mysql_class(1062), Some(ErrorClass::UniqueViolation));
+        assert_eq!(mysql_class(1452), Some(ErrorClass::ForeignKeyViolation));
+        assert_eq!(mysql_class(1213), Some(ErrorClass::Deadlock));
+        assert_eq!(mysql_class(1064), Some(ErrorClass::Syntax));
+        assert_eq!(mysql_class(1366), None);
+    }
+
+    #[cfg(feature = "sqlx-sqlite")]
+    #[test]
+    fn test_sqlite_codes() {
+        assert_eq!(sqlite_class(2067), Some(ErrorClass::UniqueViolation));
+        assert_eq!(sqlite_class(787), Some(ErrorClass::ForeignKeyViolation));
+        assert_eq!(sqlite_class(517), Some(ErrorClass::SerializationFailure));
+        // SQLITE_BUSY and SQLITE_LOCKED_SHAREDCACHE
+        assert_eq!(sqlite_class(5), Some(ErrorClass::Deadlock));
+        assert_eq!(sqlite_class(262), Some(ErrorClass::Deadlock));
+        assert_eq!(sqlite_class(19), None);
+    }
+
+    #[test]
+    fn test_no_status_code() {
+        assert_eq!(status_code(&DbErr::Custom("boom".into())), None);
+        assert_eq!(status_code(&DbErr::RecordNotFound("cake".into())), None);
+    }
+
+    #[cfg(any(
+        feature = "sqlx-mysql",
+        feature = "sqlx-postgres",
+        feature = "sqlx-sqlite"
+    ))]
+    #[test]
+    fn test_sqlstate() {
+        use std::borrow::Cow;
+
+        #[derive(Debug)]
+        struct SerializationFailure;
+
+        impl std::fmt::Display for SerializationFailure {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                f.write_str("could not serialize access due to concurrent update")
+            }
+        }
+
+        impl std::error::Error for SerializationFailure {}
+
+        impl sqlx::error::DatabaseError for SerializationFailure {
+            fn message(&self) -> &str {
+                "could not serialize access due to concurrent update"
+            }
+
+            fn code(&self) -> Option<Cow<'_, str>> {
+                Some(Cow::Borrowed("40001"))
+            }
+
+            fn as_error(&self) -> &(dyn std::error::Error + Send + Sync + 'static) {
+                self
+            }
+
+            fn as_error_mut(&mut self) -> &mut (dyn std::error::Error + Send + Sync + 'static) {
+                self
+            }
+
+            fn into_error(self: Box<Self>) -> Box<dyn std::error::Error + Send + Sync + 'static> {
+                self
+            }
+
+            fn kind(&self) -> sqlx::error::ErrorKind {
+                sqlx::error::ErrorKind::Other
+            }
+        }
+
+        let err = DbErr::Exec(RuntimeErr::SqlxError(sqlx::Error::Database(Box::new(
+            SerializationFailure,
+        ))));
+        assert_eq!(status_code(&err).as_deref(), Some("40001"));
+
+        let not_database = DbErr::Query(RuntimeErr::SqlxError(sqlx::Error::RowNotFound));
+        assert_eq!(status_code(&not_database), None);
+    }
+}
@@ -0,0 +1,591 @@
+//! Span creation and result recording shared by the traced wrappers.
+
+use std::borrow::Cow;
+use std::time::{Duration, Instant};
+
+use sea_orm::{DbBackend, DbErr, ExecResult, Statement, TransactionError};
+use tracing::{field, Span};
+
+use crate::config::{StatementMode, TracingConfig};
+use crate::entity::EntityInfo;
+use crate::error::{self, ErrorClass};
+#[cfg(feature = "opentelemetry")]
+use crate::otel;
+use crate::parameters;
+use crate::parser::{self, ParsedSql, QueryShape, SqlOperation};
+
+/// Get the database backend name for span attributes.
+fn db_system(backend: DbBackend) -> &'static str {
+    match backend {
+        DbBackend::Postgres => "postgresql",
+        DbBackend::MySql => "mysql",
+        DbBackend::Sqlite => "sqlite",
+    }
+}
+
+/// Record the database system and operation of a span under the configured conventions.
+///
+/// The span must declare `db.system`, `db.system.name`, `db.operation` and
+/// `db.operation.name`.
+pub(crate) fn record_system(
+    config: &TracingConfig,
+    span: &Span,
+    backend: DbBackend,
+    operation: &str,
+) {
+    let system = db_system(backend);
+    if config.semconv.legacy() {
+        span.record("db.system", system);
+        span.record("db.operation", operation);
+    }
+    if config.semconv.stable() {
+        span.record("db.system.name", system);
+        span.record("db.operation.name", operation);
+    }
+}
+
+/// The `db.namespace` of a statement.
+///
+/// On Postgres this is `{database}|{schema}` when both are known. On MySQL and SQLite a
+/// table qualifier names a database (or attached database), so it is the namespace.
+fn namespace(config: &TracingConfig, backend: DbBackend, parsed: &ParsedSql) -> Option<String> {
+    match (&config.database_name, &parsed.schema) {
+        (Some(database), Some(schema)) if backend == DbBackend::Postgres => {
+            Some(format!("{}|{}", database, schema))
+        }
+        (_, Some(schema)) => Some(schema.clone()),
+        (Some(database), None) => Some(database.clone()),
+        (None, None) => None,
+    }
+}
+
+/// Truncate a statement to at most `max` bytes, marker included, ending it with a marker
+/// carrying `original_len`.
+///
+/// `original_len` is the length of the statement before it was sanitized, the same
+/// length recorded as `db.statement.length`. If `max` is shorter than the marker itself,
+/// only as much of the marker as fits is kept.
+fn truncate_statement(statement: &str, original_len: usize, max: usize) -> Cow<'_, str> {
+    if statement.len() <= max {
+        return Cow::Borrowed(statement);
+    }
+    let marker = format!("... [truncated, {} bytes]", original_len);
+    if marker.len() >= max {
+        return Cow::Owned(parameters::truncate_str(&marker, max).to_string());
+    }
+    let keep = max - marker.len();
+    Cow::Owned(format!(
+        "{}{}",
+        parameters::truncate_str(statement, keep),
+        marker
+    ))
+}
+
+/// Create a tracing span for a database operation.
+pub(crate) fn create_span(
+    config: &TracingConfig,
+    backend: DbBackend,
+    stmt: &Statement,
+    parsed: &ParsedSql,
+) -> Span {
+    let span_name = parsed.span_name();
+
+    let span = tracing::info_span!(
+        "db.query",
+        otel.name = %span_name,
+        otel.kind = "client",
+        db.system = field::Empty,
+        db.system.name = field::Empty,
+        db.operation = field::Empty,
+        db.operation.name = field::Empty,
+        db.query.summary = field::Empty,
+        db.sql.table = field::Empty,
+        db.collection.name = field::Empty,
+        db.sql.schema = field::Empty,
+        db.collection.names = field::Empty,
+        db.namespace = field::Empty,
+        db.stored_procedure.name = field::Empty,
+        db.returning = field::Empty,
+        db.sea_orm.entity = field::Empty,
+        db.sea_orm.columns = field::Empty,
+        db.sea_orm.has_limit = field::Empty,
+        db.query.fingerprint = %parsed.fingerprint,
+        db.query.has_where = field::Empty,
+        db.query.has_limit = field::Empty,
+        db.query.limit = field::Empty,
+        db.query.offset = field::Empty,
+        db.query.has_order_by = field::Empty,
+        db.query.distinct = field::Empty,
+        db.query.has_group_by = field::Empty,
+        db.query.lock = field::Empty,
+        db.query.join_count = field::Empty,
+        db.query.subquery_depth = field::Empty,
+        db.statement = field::Empty,
+        db.query.text = field::Empty,
+        db.statement.length = field::Empty,
+        db.rows_affected = field::Empty,
+        db.response.returned_rows = field::Empty,
+        db.last_insert_id = field::Empty,
+        db.duration_ms = field::Empty,
+        db.name = field::Empty,
+        server.address = field::Empty,
+        server.port = field::Empty,
+        peer.service = field::Empty,
+        otel.status_code = field::Empty,
+        error.type = field::Empty,
+        error.retryable = field::Empty,
+        db.response.status_code = field::Empty,
+        error.message = field::Empty,
+        slow_query = field::Empty,
+        db.query.parameter.count = field::Empty,
+        db.query.parameter.truncated = field::Empty,
+        "db.query.parameter.0" = field::Empty,
+        "db.query.parameter.1" = field::Empty,
+        "db.query.parameter.2" = field::Empty,
+        "db.query.parameter.3" = field::Empty,
+        "db.query.parameter.4" = field::Empty,
+        "db.query.parameter.5" = field::Empty,
+        "db.query.parameter.6" = field::Empty,
+        "db.query.parameter.7" = field::Empty,
+        "db.query.parameter.8" = field::Empty,
+        "db.query.parameter.9" = field::Empty,
+        "db.query.parameter.10" = field::Empty,
+        "db.query.parameter.11" = field::Empty,
+        "db.query.parameter.12" = field::Empty,
+        "db.query.parameter.13" = field::Empty,
+        "db.query.parameter.14" = field::Empty,
+        "db.query.parameter.15" = field::Empty,
+    );
+
+    record_system(config, &span, backend, parsed.operation.as_str());
+    if config.semconv.stable() {
+        span.record("db.query.summary", span_name);
+    }
+
+    // Record table if available
+    if let Some(table) = &parsed.table {
+        if config.semconv.legacy() {
+            span.record("db.sql.table", table.as_str());
+        }
+        if config.semconv.stable() {
+            span.record("db.collection.name", table.as_str());
+        }
+    }
+
+    // Record the procedure invoked by CALL
+    if let Some(procedure) = &parsed.stored_procedure {
+        span.record("db.stored_procedure.name", procedure.as_str());
+    }
+
+    // Record the columns returned by RETURNING
+    if let Some(columns) = &parsed.returning {
+        record_list(&span, "db.returning", columns.iter().map(String::as_str));
+    }
+
+    // Record the structure of statements that read or change rows
+    if matches!(
+        parsed.operation,
+        SqlOperation::Select | SqlOperation::Update | SqlOperation::Delete
+    ) {
+        record_shape(&span, &parsed.shape);
+    }
+
+    // Record every table the statement touches
+    if !parsed.tables.is_empty() {
+        record_list(&span, "db.collection.names", parsed.collection_names());
+    }
+
+    // Record the schema qualifying the table, and the namespace it belongs to
+    if let Some(schema) = &parsed.schema {
+        span.record("db.sql.schema", schema.as_str());
+    }
+    if let Some(namespace) = namespace(config, backend, parsed) {
+        span.record("db.namespace", namespace.as_str());
+    }
+
+    record_connection(config, &span);
+
+    // Record SQL statement if configured
+    if config.log_statements {
+        let statement = match config.statement_mode {
+            StatementMode::Raw => Cow::Borrowed(stmt.sql.as_str()),
+            StatementMode::Sanitized => Cow::Owned(parser::sanitize(&stmt.sql, backend)),
+        };
+        span.record("db.statement.length", stmt.sql.len() as u64);
+        let statement = match config.max_statement_length {
+            Some(max) => truncate_statement(&statement, stmt.sql.len(), max),
+            None => Cow::Borrowed(statement.as_ref()),
+        };
+        if config.semconv.legacy() {
+            span.record("db.statement", statement.as_ref());
+        }
+        if config.semconv.stable() {
+            span.record("db.query.text", statement.as_ref());
+        }
+    }
+
+    // Record bound parameters if configured
+    if config.log_parameters {
+        if let Some(values) = &stmt.values {
+            parameters::record_parameters(
+                &span,
+                backend,
+                &stmt.sql,
+                &values.0,
+                &config.parameter_redaction,
+            );
+        }
+    }
+
+    span
+}
+
+/// Record the configured database name, server and peer service.
+///
+/// The span must declare `db.name`, `server.address`, `server.port` and `peer.service`.
+pub(crate) fn record_connection(config: &TracingConfig, span: &Span) {
+    // Record database name if configured; the stable conventions use db.namespace
+    if let Some(db_name) = &config.database_name {
+        if config.semconv.legacy() {
+            span.record("db.name", db_name.as_str());
+        }
+    }
+
+    // Record server address and port for X-Ray service map
+    if let Some(addr) = &config.server_address {
+        span.record("server.address", addr.as_str());
+    }
+    if let Some(port) = config.server_port {
+        span.record("server.port", port as i64);
+    }
+
+    // Record peer service for X-Ray trace map node naming
+    if let Some(peer) = &config.peer_service {
+        span.record("peer.service", peer.as_str());
+    }
+}
+
+/// Record the structural facts of a statement.
+fn record_shape(span: &Span, shape: &QueryShape) {
+    span.record("db.query.has_where", shape.has_where);
+    span.record("db.query.has_limit", shape.has_limit);
+    if let Some(limit) = shape.limit {
+        span.record("db.query.limit", limit);
+    }
+    if let Some(offset) = shape.offset {
+        span.record("db.query.offset", offset);
+    }
+    span.record("db.query.has_order_by", shape.has_order_by);
+    span.record("db.query.distinct", shape.distinct);
+    span.record("db.query.has_group_by", shape.has_group_by);
+    if let Some(lock) = shape.lock {
+        span.record("db.query.lock", lock.as_str());
+    }
+    span.record("db.query.join_count", shape.join_count as u64);
+    span.record("db.query.subquery_depth", shape.subquery_depth as u64);
+}
+
+/// Number of rows a statement returned or changed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum RowCount {
+    /// Rows returned by `query_one` or `query_all`.
+    Returned(u64),
+    /// Rows changed by `execute`.
+    Affected(u64),
+}
+
+impl RowCount {
+    /// The number of rows.
+    pub(crate) fn get(self) -> u64 {
+        match self {
+            RowCount::Returned(count) | RowCount::Affected(count) => count,
+        }
+    }
+}
+
+/// Record the result of a database operation in the span.
+///
+/// Returns the time spent in the database.
+pub(crate) fn record_result<T>(
+    config: &TracingConfig,
+    span: &Span,
+    result: &Result<T, DbErr>,
+    start: Instant,
+    row_count: Option<RowCount>,
+) -> Duration {
+    let elapsed = start.elapsed();
+    let duration_ms = elapsed.as_millis() as i64;
+    span.record("db.duration_ms", duration_ms);
+
+    // Record row count if available and configured
+    if config.record_row_counts {
+        match row_count {
+            Some(RowCount::Returned(count)) => {
+                if config.semconv.legacy() {
+                    span.record("db.rows_affected", count);
+                }
+                if config.semconv.stable() {
+                    span.record("db.response.returned_rows", count);
+                }
+            }
+            // The stable conventions only count returned rows, so writes keep
+            // db.rows_affected in every mode
+            Some(RowCount::Affected(count)) => {
+                span.record("db.rows_affected", count);
+            }
+            None => {}
+        }
+    }
+
+    // Check for slow query
+    if elapsed > config.slow_query_threshold {
+        span.record("slow_query", true);
+        let threshold_ms = config.slow_query_threshold.as_millis() as i64;
+        tracing::warn!(
+            parent: span,
+            duration_ms = duration_ms,
+            threshold_ms = threshold_ms,
+            "Slow query detected"
+        );
+    }
+
+    match result {
+        Ok(_) => {
+            record_ok(span);
+        }
+        Err(e) => {
+            record_error(span, e);
+            tracing::error!(
+                parent: span,
+                error = %e,
+                "Database query failed"
+            );
+        }
+    }
+
+    elapsed
+}
+
+/// Record a successful operation in the span.
+///
+/// With the `opentelemetry` feature the status is left unset: `tracing-opentelemetry`
+/// turns `otel.status_code = "OK"` into `Status::Ok`, which the OpenTelemetry spec
+/// reserves for application code.
+pub(crate) fn record_ok(span: &Span) {
+    #[cfg(not(feature = "opentelemetry"))]
+    span.record("otel.status_code", "OK");
+    #[cfg(feature = "opentelemetry")]
+    let _ = span;
+}
+
+/// Record a failed operation in the span.
+pub(crate) fn record_error(span: &Span, err: &DbErr) {
+    let class = ErrorClass::from_db_err(err);
+    record_failure(span, class.as_str(), &err.to_string());
+    span.record("error.retryable", class.is_retryable());
+    record_status_code(span, err);
+}
+
+/// Record a failed transaction closure in the span.
+///
+/// Errors returned by the closure itself are classed as [`ErrorClass::Application`].
+pub(crate) fn record_transaction_error<E: std::fmt::Debug>(span: &Span, err: &TransactionError<E>) {
+    match err {
+        TransactionError::Connection(e) => {
+            let class = ErrorClass::from_db_err(e);
+            record_failure(span, class.as_str(), &format!("{:?}", err));
+            span.record("error.retryable", class.is_retryable());
+            record_status_code(span, e);
+        }
+        TransactionError::Transaction(_) => {
+            let class = ErrorClass::Application;
+            record_failure(span, class.as_str(), &format!("{:?}", err));
+            span.record("error.retryable", class.is_retryable());
+        }
+    }
+}
+
+/// Record the code the database reported for an error.
+fn record_status_code(span: &Span, err: &DbErr) {
+    if let Some(code) = error::status_code(err) {
+        span.record("db.response.status_code", code.as_str());
+    }
+}
+
+fn record_failure(span: &Span, error_type: &'static str, message: &str) {
+    span.record("otel.status_code", "ERROR");
+    span.record("error.type", error_type);
+    span.record("error.message", message);
+    #[cfg(feature = "opentelemetry")]
+    otel::set_error(span, error_type, message);
+}
+
+/// Record a list of names comma-separated or, with the `opentelemetry` feature, as a
+/// string array attribute.
+///
+/// Only one form is recorded, so exported spans never carry the key twice with
+/// different types.
+pub(crate) fn record_list<'a>(
+    span: &Span,
+    key: &'static str,
+    values: impl IntoIterator<Item = &'a str>,
+) {
+    #[cfg(feature = "opentelemetry")]
+    otel::set_list(span, key, values);
+    #[cfg(not(feature = "opentelemetry"))]
+    span.record(
+        key,
+        values.into_iter().collect::<Vec<_>>().join(",").as_str(),
+    );
+}
+
+/// Record the SeaORM entity metadata of a query built with the entity-aware helpers.
+pub(crate) fn record_entity(span: &Span, parsed: &ParsedSql, entity: &EntityInfo) {
+    record_list(
+        span,
+        "db.sea_orm.entity",
+        entity.entities.iter().map(String::as_str),
+    );
+    if !entity.columns.is_empty() {
+        record_list(
+            span,
+            "db.sea_orm.columns",
+            entity.columns.iter().map(String::as_str),
+        );
+    }
+    if entity.is_select {
+        span.record("db.sea_orm.has_limit", parsed.shape.has_limit);
+    }
+}
+
+/// Record the key generated by an INSERT on MySQL and SQLite.
+///
+/// Postgres has no last insert id (SeaORM uses `RETURNING` instead) and
+/// `ExecResult::last_insert_id` panics there, so it is never read for Postgres.
+pub(crate) fn record_last_insert_id(
+    span: &Span,
+    backend: DbBackend,
+    result: &Result<ExecResult, DbErr>,
+) {
+    if backend == DbBackend::Postgres {
+        return;
+    }
+    if let Ok(exec) = result {
+        let id = exec.last_insert_id();
+        if id != 0 {
+            span.record("db.last_insert_id", id);
+        }
+    }
+}
+
+/// Record the result of opening a stream in the span.
+///
+/// Streams are consumed lazily, so no row count is available. Returns the time spent
+/// opening the stream.
+pub(crate) fn record_stream_result<T>(
+    config: &TracingConfig,
+    span: &Span,
+    result: &Result<T, DbErr>,
+    start: Instant,
+) -> Duration {
+    let elapsed = start.elapsed();
+    let duration_ms = elapsed.as_millis() as i64;
+    span.record("db.duration_ms", duration_ms);
+
+    if elapsed > config.slow_query_threshold {
+        span.record("slow_query", true);
+    }
+
+    match result {
+        Ok(_) => {
+            record_ok(span);
+        }
+        Err(e) => record_error(span, e),
+    }
+
+    elapsed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::test_support::Capture;
+
+    #[test]
+    fn test_namespace() {
+        let config = TracingConfig::default().with_database_name("app");
+        let qualified = ParsedSql::parse("SELECT * FROM billing.invoices", DbBackend::Postgres);
+        let unqualified = ParsedSql::parse("SELECT * FROM invoices", DbBackend::Postgres);
+
+        assert_eq!(
+            namespace(&config, DbBackend::Postgres, &qualified).as_deref(),
+            Some("app|billing")
+        );
+        assert_eq!(
+            namespace(&config, DbBackend::MySql, &qualified).as_deref(),
+            Some("billing")
+        );
+        assert_eq!(
+            namespace(&config, DbBackend::Postgres, &unqualified).as_deref(),
+            Some("app")
+        );
+        assert_eq!(
+            namespace(&TracingConfig::default(), DbBackend::Postgres, &unqualified),
+            None
+        );
+    }
+
+    #[cfg(not(feature = "opentelemetry"))]
+    #[test]
+    fn test_record_list() {
+        let (capture, _guard) = Capture::install();
+
+        let span = tracing::info_span!("db.query", db.collection.names = field::Empty);
+        record_list(&span, "db.collection.names", ["orders", "users"]);
+
+        let spans = capture.spans("db.query");
+        assert_eq!(spans[0].get("db.collection.names"), Some("orders,users"));
+    }
+
+    #[test]
+    fn test_statement_length_is_original() {
+        let (capture, _guard) = Capture::install();
+        let config = TracingConfig::default()
+            .with_statement_logging(true)
+            .with_statement_mode(StatementMode::Sanitized)
+            .with_max_statement_length(30);
+        let sql = "SELECT * FROM users WHERE name = 'a long literal value'";
+        let stmt = Statement::from_string(DbBackend::Postgres, sql);
+        let parsed = ParsedSql::parse(sql, DbBackend::Postgres);
+
+        let _span = create_span(&config, DbBackend::Postgres, &stmt, &parsed);
+
+        let span = &capture.spans("db.query")[0];
+        assert_eq!(
+            span.get("db.statement.length"),
+            Some(sql.len().to_string().as_str())
+        );
+        // The marker carries the same original length
+        let statement = span.get("db.statement").unwrap();
+        assert!(statement.len() <= 30);
+        assert!(statement.ends_with(&format!("[truncated, {} bytes]", sql.len())));
+    }
+
+    #[test]
+    fn test_truncate_statement() {
+        assert_eq!(truncate_statement("SELECT 1", 8, 100), "SELECT 1");
+        // The marker counts towards the limit
+        let truncated = truncate_statement("SELECT * FROM users WHERE id = 1 AND active", 43, 33);
+        assert_eq!(truncated, "SELECT *... [truncated, 43 bytes]");
+        assert_eq!(truncated.len(), 33);
+        // 'ü' is two bytes and is not split
+        assert_eq!(
+            truncate_statement("SELECT 'über' FROM users WHERE id = 1", 38, 34),
+            "SELECT '... [truncated, 38 bytes]"
+        );
+        // Too short for the marker, which is cut to fit
+        assert_eq!(truncate_statement("SELECT * FROM users", 19, 4), "... ");
+        assert_eq!(truncate_statement("SELECT * FROM users", 19, 0), "");
+    }
+}
@@ -29,6 +29,37 @@
 //! let users = Users::find().all(&traced_db).await?;
 //! ```
 //!
+//! ## Transactions
+//!
+//! Transactions started from a `TracedConnection` are wrapped in a `TracedTransaction`,
+//! so every statement executed inside them is traced as well:
+//!
+//! ```rust,ignore
+//! let txn = traced_db.begin().await?;
+//! user.insert(&txn).await?;
+//! txn.commit().await?;
+//!
+//! traced_db
+//!     .transaction::<_, _, DbErr>(|txn| {
+//!         Box::pin(async move {
+//!             user.insert(txn).await?;
+//!             Ok(())
+//!         })
+//!     })
+//!     .await?;
+//! ```
+//!
+//! ## Entity-aware Queries
+//!
+//! [`TracedSelectExt`] and [`TracedExecExt`] run SeaORM queries with the entity name,
+//! the selected columns and LIMIT presence recorded as `db.sea_orm.*` attributes:
+//!
+//! ```rust,ignore
+//! use sea_orm_tracing::prelude::*;
+//!
+//! let cakes = Cake::find().traced_all(&traced_db).await?;
+//! ```
+//!
 //! ## Configuration
 //!
 //! ```rust,ignore
@@ -44,26 +75,75 @@
 //!
 //! ## Span Attributes
 //!
-//! The following OpenTelemetry semantic convention attributes are recorded:
+//! The following OpenTelemetry semantic convention attributes are recorded. They use the
+//! legacy attribute names unless [`SemconvVersion`] selects the stable conventions
+//! (`db.system.name`, `db.query.text`, ...), or `OTEL_SEMCONV_STABILITY_OPT_IN` is set
+//! to `database` or `database/dup`.
+//!
+//! With the `opentelemetry` feature, the span status, `exception` events and list-valued
+//! attributes such as `db.collection.names` are recorded through `tracing-opentelemetry`
+//! directly; list attributes are then no longer recorded as plain span fields.
 //!
 //! | Attribute | Description |
 //! |-----------|-------------|
 //! | `db.system` | Always "postgresql", "mysql", or "sqlite" |
-//! | `db.operation` | SQL operation (SELECT, INSERT, UPDATE, DELETE) |
+//! | `db.operation` | SQL operation (SELECT, INSERT, UPSERT, MERGE, CREATE INDEX, CALL, ...) |
 //! | `db.sql.table` | Target table name (when detectable) |
-//! | `db.statement` | Full SQL query (when enabled) |
+//! | `db.collection.names` | All tables the statement reads or writes (comma-separated) |
+//! | `db.sql.schema` | Schema qualifying the table (e.g. `public` in `public.users`) |
+//! | `db.namespace` | Database and/or schema the statement operates on |
+//! | `db.stored_procedure.name` | Procedure invoked by a `CALL` statement |
+//! | `db.returning` | Columns returned by a `RETURNING` clause |
+//! | `db.query.fingerprint` | Stable hash of the normalized statement |
+//! | `db.query.has_where` | Whether the statement has a WHERE clause |
+//! | `db.query.has_limit` | Whether the statement has a LIMIT or `FETCH FIRST` clause |
+//! | `db.query.limit`, `db.query.offset` | Literal LIMIT and OFFSET values |
+//! | `db.query.has_order_by` | Whether the statement has an ORDER BY clause |
+//! | `db.query.distinct` | Whether the statement is a `SELECT DISTINCT` |
+//! | `db.query.has_group_by` | Whether the statement has a GROUP BY clause |
+//! | `db.query.lock` | Row locking clause (`FOR UPDATE`, `FOR SHARE`, ...) |
+//! | `db.query.join_count` | Number of joins, including those in subqueries |
+//! | `db.query.subquery_depth` | How deeply subqueries are nested |
+//! | `db.statement` | Full SQL query (when enabled, optionally sanitized and truncated) |
+//! | `db.statement.length` | Length of the original statement in bytes (when enabled) |
+//! | `db.query.parameter.<index>` | Bound parameter values (when enabled; first 16 without `opentelemetry`) |
+//! | `db.query.parameter.truncated` | Whether parameters past the 16th were dropped |
+//! | `db.query.parameter.count` | Number of bound parameters (when enabled) |
 //! | `db.rows_affected` | Number of rows returned/affected |
+//! | `db.last_insert_id` | Key generated by an insert on MySQL and SQLite (when non-zero) |
+//! | `otel.kind` | Always "client" |
 //! | `otel.status_code` | "OK" or "ERROR" |
+//! | `error.type` | [`ErrorClass`] of the failure, e.g. `pool_timeout` or `unique_violation` |
+//! | `error.retryable` | Whether the failed operation may succeed if retried |
+//! | `db.response.status_code` | SQLSTATE, MySQL error number or SQLite result code (with a `sqlx-*` feature) |
 //! | `error.message` | Error details (on failure) |
 
+mod cache;
 mod config;
 mod connection;
-mod parser;
+mod entity;
+mod error;
+mod instrument;
+#[cfg(feature = "opentelemetry")]
+mod otel;
+mod parameters;
+pub mod parser;
+#[cfg(test)]
+mod test_support;
+mod transaction;
 
-pub use config::TracingConfig;
+pub use cache::ParseCacheStats;
+pub use config::{ParameterRedaction, SemconvVersion, StatementMode, TracingConfig};
 pub use connection::{TracedConnection, TracingExt};
+pub use entity::{TracedConnectionTrait, TracedExecExt, TracedSelectExt};
+pub use error::ErrorClass;
+pub use parser::{fingerprint, SqlClassifier};
+pub use transaction::TracedTransaction;
 
 /// Prelude module for convenient imports
 pub mod prelude {
-    pub use crate::{TracedConnection, TracingConfig, TracingExt};
+    pub use crate::{
+        TracedConnection, TracedExecExt, TracedSelectExt, TracedTransaction, TracingConfig,
+        TracingExt,
+    };
 }
@@ -0,0 +1,135 @@
+//! Native OpenTelemetry recording, enabled with the `opentelemetry` feature.
+//!
+//! `tracing-opentelemetry` turns span fields into string, integer and boolean
+//! attributes. These helpers go through [`OpenTelemetrySpanExt`] instead, so spans get a
+//! real status with its description, `exception` events and list-valued attributes.
+//! Without a `tracing-opentelemetry` layer installed they do nothing.
+
+use opentelemetry::trace::Status;
+use opentelemetry::{Array, KeyValue, StringValue, Value};
+use tracing::Span;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+/// Mark the span as failed and add an `exception` event describing the error.
+pub(crate) fn set_error(span: &Span, error_type: &'static str, message: &str) {
+    span.set_status(Status::error(message.to_string()));
+    span.add_event(
+        "exception",
+        vec![
+            KeyValue::new("exception.type", error_type),
+            KeyValue::new("exception.message", message.to_string()),
+        ],
+    );
+}
+
+/// Set a string attribute whose key is not declared as a span field.
+pub(crate) fn set_string(span: &Span, key: String, value: String) {
+    span.set_attribute(key, value);
+}
+
+/// Set a string array attribute.
+pub(crate) fn set_list<'a>(
+    span: &Span,
+    key: &'static str,
+    values: impl IntoIterator<Item = &'a str>,
+) {
+    let values: Vec<StringValue> = values
+        .into_iter()
+        .map(|value| StringValue::from(value.to_string()))
+        .collect();
+    span.set_attribute(key, Value::Array(Array::String(values)));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::ErrorClass;
+    use crate::{instrument, parameters};
+    use opentelemetry::trace::{Status, TracerProvider as _};
+    use opentelemetry_sdk::trace::{InMemorySpanExporter, SdkTracerProvider};
+    use sea_orm::DbBackend;
+    use tracing::field;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    #[test]
+    fn test_status_events_and_lists() {
+        let exporter = InMemorySpanExporter::default();
+        let provider = SdkTracerProvider::builder()
+            .with_simple_exporter(exporter.clone())
+            .build();
+        let subscriber = tracing_subscriber::registry()
+            .with(tracing_opentelemetry::layer().with_tracer(provider.tracer("test")));
+
+        tracing::subscriber::with_default(subscriber, || {
+            let span = tracing::info_span!("db.query", db.collection.names = field::Empty);
+            instrument::record_list(&span, "db.collection.names", ["orders", "users"]);
+            let error_type = ErrorClass::NotFound.as_str();
+            set_error(&span, error_type, "RecordNotFound Error: cake");
+            drop(span);
+
+            let span = tracing::info_span!("db.query", otel.status_code = field::Empty);
+            instrument::record_ok(&span);
+        });
+
+        let spans = exporter.get_finished_spans().unwrap();
+        // Successful spans are left for the application to mark as OK
+        assert_eq!(spans[1].status, Status::Unset);
+
+        let span = &spans[0];
+        assert_eq!(span.status, Status::error("RecordNotFound Error: cake"));
+
+        // The list is exported once, as an array
+        let tables: Vec<_> = span
+            .attributes
+            .iter()
+            .filter(|kv| kv.key.as_str() == "db.collection.names")
+            .collect();
+        assert_eq!(tables.len(), 1);
+        assert_eq!(
+            tables[0].value,
+            Value::Array(Array::String(vec!["orders".into(), "users".into()]))
+        );
+
+        let event = &span.events[0];
+        assert_eq!(event.name, "exception");
+        assert!(event.attributes.contains(&KeyValue::new(
+            "exception.type",
+            ErrorClass::NotFound.as_str()
+        )));
+    }
+
+    #[test]
+    fn test_parameters_past_declared_fields() {
+        let exporter = InMemorySpanExporter::default();
+        let provider = SdkTracerProvider::builder()
+            .with_simple_exporter(exporter.clone())
+            .build();
+        let subscriber = tracing_subscriber::registry()
+            .with(tracing_opentelemetry::layer().with_tracer(provider.tracer("test")));
+        let values: Vec<sea_orm::Value> = (0..20).map(|i| sea_orm::Value::Int(Some(i))).collect();
+
+        tracing::subscriber::with_default(subscriber, || {
+            let span = tracing::info_span!(
+                "db.query",
+                db.query.parameter.truncated = field::Empty,
+                "db.query.parameter.15" = field::Empty,
+            );
+            let redaction = Default::default();
+            parameters::record_parameters(&span, DbBackend::Postgres, "", &values, &redaction);
+        });
+
+        let spans = exporter.get_finished_spans().unwrap();
+        let attribute = |key: &str| {
+            spans[0]
+                .attributes
+                .iter()
+                .find(|kv| kv.key.as_str() == key)
+                .map(|kv| kv.value.clone())
+        };
+        assert_eq!(attribute("db.query.parameter.15"), Some("15".into()));
+        // Every parameter is exported, so none are flagged as dropped
+        assert_eq!(attribute("db.query.parameter.19"), Some("19".into()));
+        assert_eq!(attribute("db.query.parameter.truncated"), None);
+    }
+}
@@ -0,0 +1,292 @@
+//! Bound parameter recording for query spans.
+
+use std::fmt::{self, Write};
+
+use sea_orm::{DbBackend, Value};
+use tracing::Span;
+
+use crate::config::ParameterRedaction;
+#[cfg(feature = "opentelemetry")]
+use crate::otel;
+use crate::parser;
+
+/// Recorded in place of parameters bound to masked columns.
+pub(crate) const REDACTED: &str = "[REDACTED]";
+
+/// Number of bound parameters declared as span fields.
+///
+/// Span fields must be declared up front, so only the first parameters of large
+/// statements (e.g. `insert_many`) are recorded as fields. With the `opentelemetry`
+/// feature the rest are set as attributes directly; otherwise they are dropped and
+/// `db.query.parameter.truncated` is recorded.
+pub(crate) const MAX_RECORDED_PARAMETERS: usize = 16;
+
+/// Span field names for bound parameters, following the OpenTelemetry
+/// `db.query.parameter.<key>` convention with the zero-based index as key.
+pub(crate) const PARAMETER_FIELDS: [&str; MAX_RECORDED_PARAMETERS] = [
+    "db.query.parameter.0",
+    "db.query.parameter.1",
+    "db.query.parameter.2",
+    "db.query.parameter.3",
+    "db.query.parameter.4",
+    "db.query.parameter.5",
+    "db.query.parameter.6",
+    "db.query.parameter.7",
+    "db.query.parameter.8",
+    "db.query.parameter.9",
+    "db.query.parameter.10",
+    "db.query.parameter.11",
+    "db.query.parameter.12",
+    "db.query.parameter.13",
+    "db.query.parameter.14",
+    "db.query.parameter.15",
+];
+
+/// Format a bound value the way the backend would write it as an SQL literal.
+///
+/// Strings, JSON, UUIDs and date/time values are quoted, byte strings use the
+/// backend's hex literal syntax and `None` values are written as `NULL`.
+pub(crate) fn format_value(backend: DbBackend, value: &Value) -> String {
+    backend.get_query_builder().value_to_string(value)
+}
+
+/// The name of a value's type, e.g. `String`, `BigInt` or `ChronoDateTimeUtc`.
+pub(crate) fn type_name(value: &Value) -> String {
+    /// Captures the variant name from the derived `Debug` output and stops before
+    /// the value itself is formatted.
+    struct VariantName(String);
+
+    impl Write for VariantName {
+        fn write_str(&mut self, s: &str) -> fmt::Result {
+            match s.find('(') {
+                Some(end) => {
+                    self.0.push_str(&s[..end]);
+                    Err(fmt::Error)
+                }
+                None => {
+                    self.0.push_str(s);
+                    Ok(())
+                }
+            }
+        }
+    }
+
+    let mut name = VariantName(String::new());
+    let _ = write!(name, "{:?}", value);
+    name.0
+}
+
+/// Truncate `s` to at most `max` bytes without splitting a UTF-8 character.
+pub(crate) fn truncate_str(s: &str, max: usize) -> &str {
+    if s.len() <= max {
+        return s;
+    }
+    let mut end = max;
+    while !s.is_char_boundary(end) {
+        end -= 1;
+    }
+    &s[..end]
+}
+
+/// Format a value, truncating long strings and byte strings to `max` bytes.
+///
+/// Truncated values are followed by a marker with the original length.
+fn format_truncated(backend: DbBackend, value: &Value, max: usize) -> String {
+    let (truncated, len) = match value {
+        Value::String(Some(s)) if s.len() > max => (
+            Value::String(Some(Box::new(truncate_str(s, max).to_string()))),
+            s.len(),
+        ),
+        Value::Bytes(Some(b)) if b.len() > max => {
+            (Value::Bytes(Some(Box::new(b[..max].to_vec()))), b.len())
+        }
+        _ => return format_value(backend, value),
+    };
+    format!(
+        "{}... [truncated, {} bytes]",
+        format_value(backend, &truncated),
+        len
+    )
+}
+
+/// Format a parameter according to the redaction policy.
+fn redact(
+    backend: DbBackend,
+    value: &Value,
+    column: Option<&str>,
+    redaction: &ParameterRedaction,
+) -> String {
+    if column.is_some_and(|c| redaction.is_masked(c)) {
+        REDACTED.to_string()
+    } else if redaction.types_only {
+        type_name(value)
+    } else if let Some(max) = redaction.max_length {
+        format_truncated(backend, value, max)
+    } else {
+        format_value(backend, value)
+    }
+}
+
+/// Record bound parameters as `db.query.parameter.<index>` span attributes, and their
+/// total number as `db.query.parameter.count`.
+///
+/// Parameters past the declared fields are set through `tracing-opentelemetry` with the
+/// `opentelemetry` feature, and flagged with `db.query.parameter.truncated` without it.
+pub(crate) fn record_parameters(
+    span: &Span,
+    backend: DbBackend,
+    sql: &str,
+    values: &[Value],
+    redaction: &ParameterRedaction,
+) {
+    let columns = if redaction.masked_columns.is_empty() {
+        Vec::new()
+    } else {
+        parser::parameter_columns(sql, backend)
+    };
+
+    let format = |index: usize, value: &Value| {
+        let column = columns.get(index).and_then(Option::as_deref);
+        redact(backend, value, column, redaction)
+    };
+
+    span.record("db.query.parameter.count", values.len());
+    for (index, (field, value)) in PARAMETER_FIELDS.iter().zip(values).enumerate() {
+        span.record(*field, format(index, value).as_str());
+    }
+
+    if values.len() > MAX_RECORDED_PARAMETERS {
+        #[cfg(feature = "opentelemetry")]
+        for (index, value) in values.iter().enumerate().skip(MAX_RECORDED_PARAMETERS) {
+            let key = format!("db.query.parameter.{}", index);
+            otel::set_string(span, key, format(index, value));
+        }
+        #[cfg(not(feature = "opentelemetry"))]
+        span.record("db.query.parameter.truncated", true);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use tracing::field;
+
+    use crate::test_support::Capture;
+
+    #[test]
+    fn test_format_scalars() {
+        let backend = DbBackend::Postgres;
+        assert_eq!(format_value(backend, &Value::Int(Some(42))), "42");
+        assert_eq!(format_value(backend, &Value::Bool(Some(true))), "TRUE");
+        assert_eq!(format_value(backend, &Value::Double(Some(1.5))), "1.5");
+        assert_eq!(
+            format_value(backend, &Value::String(Some(Box::new("alice".into())))),
+            "'alice'"
+        );
+    }
+
+    #[test]
+    fn test_format_null() {
+        assert_eq!(format_value(DbBackend::Postgres, &Value::Int(None)), "NULL");
+        assert_eq!(format_value(DbBackend::MySql, &Value::String(None)), "NULL");
+    }
+
+    #[test]
+    fn test_format_bytes_per_backend() {
+        let bytes = Value::Bytes(Some(Box::new(vec![0xde, 0xad])));
+        assert_eq!(format_value(DbBackend::Postgres, &bytes), "'\\xDEAD'");
+        assert_eq!(format_value(DbBackend::MySql, &bytes), "x'DEAD'");
+        assert_eq!(format_value(DbBackend::Sqlite, &bytes), "x'DEAD'");
+    }
+
+    #[test]
+    fn test_format_json_and_uuid() {
+        let json: sea_orm::prelude::Json = r#"{"a":1}"#.parse().unwrap();
+        let json = Value::Json(Some(Box::new(json)));
+        assert_eq!(format_value(DbBackend::Postgres, &json), r#"E'{\"a\":1}'"#);
+
+        let uuid = sea_orm::prelude::Uuid::nil();
+        assert_eq!(
+            format_value(DbBackend::Postgres, &Value::Uuid(Some(Box::new(uuid)))),
+            "'00000000-0000-0000-0000-000000000000'"
+        );
+    }
+
+    #[test]
+    fn test_type_name() {
+        assert_eq!(type_name(&Value::Int(Some(1))), "Int");
+        assert_eq!(type_name(&Value::String(None)), "String");
+        assert_eq!(type_name(&Value::Bytes(Some(Box::new(vec![1])))), "Bytes");
+        let uuid = Value::Uuid(Some(Box::new(sea_orm::prelude::Uuid::nil())));
+        assert_eq!(type_name(&uuid), "Uuid");
+    }
+
+    #[test]
+    fn test_parameter_count() {
+        let (capture, _guard) = Capture::install();
+        let values: Vec<Value> = (0..20).map(|i| Value::Int(Some(i))).collect();
+
+        let span = tracing::info_span!(
+            "db.query",
+            db.query.parameter.count = field::Empty,
+            db.query.parameter.truncated = field::Empty,
+            "db.query.parameter.0" = field::Empty,
+            "db.query.parameter.15" = field::Empty,
+        );
+        let redaction = ParameterRedaction::default();
+        record_parameters(&span, DbBackend::Postgres, "", &values, &redaction);
+        let short = tracing::info_span!("db.query", db.query.parameter.truncated = field::Empty,);
+        record_parameters(&short, DbBackend::Postgres, "", &values[..16], &redaction);
+
+        let spans = capture.spans("db.query");
+        let span = &spans[0];
+        assert_eq!(span.get("db.query.parameter.count"), Some("20"));
+        assert_eq!(span.get("db.query.parameter.0"), Some("0"));
+        assert_eq!(span.get("db.query.parameter.15"), Some("15"));
+        // Without the `opentelemetry` feature the remaining parameters are dropped
+        #[cfg(not(feature = "opentelemetry"))]
+        assert_eq!(span.get("db.query.parameter.truncated"), Some("true"));
+        assert_eq!(spans[1].get("db.query.parameter.truncated"), None);
+    }
+
+    #[test]
+    fn test_truncate_respects_char_boundaries() {
+        assert_eq!(truncate_str("hello", 10), "hello");
+        assert_eq!(truncate_str("hello", 3), "hel");
+        // 'é' is two bytes; cutting at byte 2 would split it
+        assert_eq!(truncate_str("héllo", 2), "h");
+    }
+
+    #[test]
+    fn test_redaction_policies() {
+        let backend = DbBackend::Postgres;
+        let value = Value::String(Some(Box::new("secret-value".into())));
+
+        let masked = ParameterRedaction {
+            masked_columns: vec!["Password".into()],
+            ..Default::default()
+        };
+        assert_eq!(redact(backend, &value, Some("password"), &masked), REDACTED);
+        assert_eq!(
+            redact(backend, &value, Some("name"), &masked),
+            "'secret-value'"
+        );
+
+        let types_only = ParameterRedaction {
+            types_only: true,
+            ..Default::default()
+        };
+        assert_eq!(redact(backend, &value, None, &types_only), "String");
+
+        let truncated = ParameterRedaction {
+            max_length: Some(6),
+            ..Default::default()
+        };
+        assert_eq!(
+            redact(backend, &value, None, &truncated),
+            "'secret'... [truncated, 12 bytes]"
+        );
+        assert_eq!(redact(backend, &Value::Int(Some(7)), None, &truncated), "7");
+    }
+}
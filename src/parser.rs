@@ -1,22 +1,68 @@
 //! SQL parsing utilities for extracting operation type and table names.
+//!
+//! Statements are analyzed from the tokens produced by a backend-aware lexer. With the
+//! `sqlparser` feature enabled, statements are first parsed with sqlparser-rs using the
+//! dialect of the backend. The regex-based [`parse_operation`] and [`extract_table`]
+//! remain as a fast fallback when neither can make sense of a statement.
+//!
+//! [`ParsedSql::parse`] is what the traced connections use to label spans. Register a
+//! [`SqlClassifier`] with
+//! [`TracingConfig::with_classifier`](crate::TracingConfig::with_classifier) to adjust
+//! its results, e.g. to label statements that follow project-specific conventions.
+
+mod analyzer;
+pub(crate) mod lexer;
+#[cfg(feature = "sqlparser")]
+mod sql_ast;
 
 use once_cell::sync::Lazy;
 use regex::Regex;
+use sea_orm::DbBackend;
+
+use self::lexer::{Token, TokenKind};
 
 /// SQL operation types
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum SqlOperation {
     Select,
     Insert,
+    /// `INSERT ... ON CONFLICT` or MySQL `INSERT ... ON DUPLICATE KEY UPDATE`
+    Upsert,
+    /// MySQL/SQLite `REPLACE INTO` and SQLite `INSERT OR REPLACE`
+    Replace,
     Update,
     Delete,
+    Merge,
+    /// `CREATE TABLE` and other `CREATE` statements without their own variant
     Create,
+    CreateIndex,
+    CreateView,
+    CreateSchema,
+    /// `DROP TABLE` and other `DROP` statements without their own variant
     Drop,
+    DropIndex,
+    DropView,
+    DropSchema,
     Alter,
     Truncate,
+    /// Stored procedure call; the procedure is recorded as `db.stored_procedure.name`
+    Call,
+    /// `EXPLAIN` and MySQL `DESCRIBE`
+    Explain,
+    Lock,
+    Vacuum,
+    Analyze,
+    Copy,
+    Pragma,
+    Show,
+    Grant,
+    Revoke,
     Begin,
     Commit,
     Rollback,
+    Savepoint,
+    ReleaseSavepoint,
+    RollbackToSavepoint,
     Set,
     Other,
 }
@@ -27,19 +73,55 @@ impl SqlOperation {
         match self {
             SqlOperation::Select => "SELECT",
             SqlOperation::Insert => "INSERT",
+            SqlOperation::Upsert => "UPSERT",
+            SqlOperation::Replace => "REPLACE",
             SqlOperation::Update => "UPDATE",
             SqlOperation::Delete => "DELETE",
+            SqlOperation::Merge => "MERGE",
             SqlOperation::Create => "CREATE",
+            SqlOperation::CreateIndex => "CREATE INDEX",
+            SqlOperation::CreateView => "CREATE VIEW",
+            SqlOperation::CreateSchema => "CREATE SCHEMA",
             SqlOperation::Drop => "DROP",
+            SqlOperation::DropIndex => "DROP INDEX",
+            SqlOperation::DropView => "DROP VIEW",
+            SqlOperation::DropSchema => "DROP SCHEMA",
             SqlOperation::Alter => "ALTER",
             SqlOperation::Truncate => "TRUNCATE",
+            SqlOperation::Call => "CALL",
+            SqlOperation::Explain => "EXPLAIN",
+            SqlOperation::Lock => "LOCK",
+            SqlOperation::Vacuum => "VACUUM",
+            SqlOperation::Analyze => "ANALYZE",
+            SqlOperation::Copy => "COPY",
+            SqlOperation::Pragma => "PRAGMA",
+            SqlOperation::Show => "SHOW",
+            SqlOperation::Grant => "GRANT",
+            SqlOperation::Revoke => "REVOKE",
             SqlOperation::Begin => "BEGIN",
             SqlOperation::Commit => "COMMIT",
             SqlOperation::Rollback => "ROLLBACK",
+            SqlOperation::Savepoint => "SAVEPOINT",
+            SqlOperation::ReleaseSavepoint => "RELEASE SAVEPOINT",
+            SqlOperation::RollbackToSavepoint => "ROLLBACK TO SAVEPOINT",
             SqlOperation::Set => "SET",
             SqlOperation::Other => "QUERY",
         }
     }
+
+    /// Returns `true` for operations that modify table data.
+    pub fn is_write(&self) -> bool {
+        matches!(
+            self,
+            SqlOperation::Insert
+                | SqlOperation::Upsert
+                | SqlOperation::Replace
+                | SqlOperation::Update
+                | SqlOperation::Delete
+                | SqlOperation::Merge
+                | SqlOperation::Truncate
+        )
+    }
 }
 
 impl std::fmt::Display for SqlOperation {
@@ -49,82 +131,176 @@ impl std::fmt::Display for SqlOperation {
 }
 
 // Regex patterns for table extraction (compiled once)
-// Using raw string literals with proper escaping for regex character classes
-static SELECT_TABLE_REGEX: Lazy<Regex> = Lazy::new(|| {
-    Regex::new(r#"(?i)\bFROM\s+[`"\[]?(\w+)[`"\]]?"#).unwrap()
-});
+// Table names may be schema-qualified and each part may be quoted with "", `` or [].
+const QUALIFIED_NAME: &str =
+    r#"((?:"[^"]+"|`[^`]+`|\[[^\]]+\]|\w+)(?:\s*\.\s*(?:"[^"]+"|`[^`]+`|\[[^\]]+\]|\w+))*)"#;
 
-static INSERT_TABLE_REGEX: Lazy<Regex> = Lazy::new(|| {
-    Regex::new(r#"(?i)\bINSERT\s+INTO\s+[`"\[]?(\w+)[`"\]]?"#).unwrap()
-});
+fn table_regex(prefix: &str) -> Regex {
+    Regex::new(&format!("(?i){}{}", prefix, QUALIFIED_NAME)).unwrap()
+}
 
-static UPDATE_TABLE_REGEX: Lazy<Regex> = Lazy::new(|| {
-    Regex::new(r#"(?i)\bUPDATE\s+[`"\[]?(\w+)[`"\]]?"#).unwrap()
-});
+static NAME_PART_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#""([^"]+)"|`([^`]+)`|\[([^\]]+)\]|(\w+)"#).unwrap());
 
-static DELETE_TABLE_REGEX: Lazy<Regex> = Lazy::new(|| {
-    Regex::new(r#"(?i)\bDELETE\s+FROM\s+[`"\[]?(\w+)[`"\]]?"#).unwrap()
-});
+static SELECT_TABLE_REGEX: Lazy<Regex> = Lazy::new(|| table_regex(r"\bFROM\s+"));
+
+static INSERT_TABLE_REGEX: Lazy<Regex> =
+    Lazy::new(|| table_regex(r"\b(?:INSERT(?:\s+OR\s+\w+)?|REPLACE|MERGE)\s+INTO\s+"));
+
+static UPDATE_TABLE_REGEX: Lazy<Regex> = Lazy::new(|| table_regex(r"\bUPDATE\s+"));
+
+static DELETE_TABLE_REGEX: Lazy<Regex> = Lazy::new(|| table_regex(r"\bDELETE\s+FROM\s+"));
 
 static CREATE_TABLE_REGEX: Lazy<Regex> = Lazy::new(|| {
-    Regex::new(r#"(?i)\bCREATE\s+(?:TEMP(?:ORARY)?\s+)?TABLE\s+(?:IF\s+NOT\s+EXISTS\s+)?[`"\[]?(\w+)[`"\]]?"#).unwrap()
+    table_regex(r"\bCREATE\s+(?:TEMP(?:ORARY)?\s+)?TABLE\s+(?:IF\s+NOT\s+EXISTS\s+)?")
 });
 
-static DROP_TABLE_REGEX: Lazy<Regex> = Lazy::new(|| {
-    Regex::new(r#"(?i)\bDROP\s+TABLE\s+(?:IF\s+EXISTS\s+)?[`"\[]?(\w+)[`"\]]?"#).unwrap()
-});
+static DROP_TABLE_REGEX: Lazy<Regex> =
+    Lazy::new(|| table_regex(r"\bDROP\s+TABLE\s+(?:IF\s+EXISTS\s+)?"));
 
-static ALTER_TABLE_REGEX: Lazy<Regex> = Lazy::new(|| {
-    Regex::new(r#"(?i)\bALTER\s+TABLE\s+[`"\[]?(\w+)[`"\]]?"#).unwrap()
-});
+static UPSERT_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?i)\bON\s+(?:CONFLICT|DUPLICATE\s+KEY)\b").unwrap());
 
-static TRUNCATE_TABLE_REGEX: Lazy<Regex> = Lazy::new(|| {
-    Regex::new(r#"(?i)\bTRUNCATE\s+(?:TABLE\s+)?[`"\[]?(\w+)[`"\]]?"#).unwrap()
-});
+static ALTER_TABLE_REGEX: Lazy<Regex> = Lazy::new(|| table_regex(r"\bALTER\s+TABLE\s+"));
+
+static TRUNCATE_TABLE_REGEX: Lazy<Regex> = Lazy::new(|| table_regex(r"\bTRUNCATE\s+(?:TABLE\s+)?"));
 
 /// Parse the SQL operation type from a query string.
+///
+/// Leading comments and parentheses are skipped, and for statements starting with
+/// `WITH` the operation is taken from the statement following the CTEs.
 pub fn parse_operation(sql: &str) -> SqlOperation {
-    let trimmed = sql.trim_start();
+    let trimmed = skip_leading_trivia(sql);
     let upper_start: String = trimmed.chars().take(15).collect::<String>().to_uppercase();
 
-    if upper_start.starts_with("SELECT") || upper_start.starts_with("WITH") {
-        SqlOperation::Select
-    } else if upper_start.starts_with("INSERT") {
-        SqlOperation::Insert
-    } else if upper_start.starts_with("UPDATE") {
-        SqlOperation::Update
-    } else if upper_start.starts_with("DELETE") {
-        SqlOperation::Delete
-    } else if upper_start.starts_with("CREATE") {
-        SqlOperation::Create
-    } else if upper_start.starts_with("DROP") {
-        SqlOperation::Drop
-    } else if upper_start.starts_with("ALTER") {
-        SqlOperation::Alter
-    } else if upper_start.starts_with("TRUNCATE") {
-        SqlOperation::Truncate
-    } else if upper_start.starts_with("BEGIN") || upper_start.starts_with("START") {
-        SqlOperation::Begin
-    } else if upper_start.starts_with("COMMIT") {
-        SqlOperation::Commit
-    } else if upper_start.starts_with("ROLLBACK") {
-        SqlOperation::Rollback
-    } else if upper_start.starts_with("SET") {
-        SqlOperation::Set
+    if upper_start.starts_with("WITH") {
+        // Quoting differences between backends do not matter for finding the end of
+        // the CTEs, so the Postgres lexer rules are used.
+        let tokens = lexer::significant_tokens(trimmed, DbBackend::Postgres);
+        let (start, _) = analyzer::main_statement(&tokens);
+        match tokens.get(start) {
+            Some(token) if !token.is_keyword("WITH") => parse_operation(&trimmed[token.start..]),
+            _ => SqlOperation::Select,
+        }
     } else {
-        SqlOperation::Other
+        let words: Vec<String> = trimmed
+            .split(|c: char| !(c.is_alphanumeric() || c == '_'))
+            .filter(|w| !w.is_empty())
+            .take(8)
+            .map(str::to_ascii_uppercase)
+            .collect();
+        match classify(&words) {
+            SqlOperation::Insert if UPSERT_REGEX.is_match(trimmed) => SqlOperation::Upsert,
+            operation => operation,
+        }
+    }
+}
+
+/// Classify a statement from its leading words, upper-cased.
+pub(crate) fn classify<S: AsRef<str>>(words: &[S]) -> SqlOperation {
+    let word = |i: usize| words.get(i).map_or("", |w| w.as_ref());
+
+    // The kind of object created or dropped, after modifiers like `OR REPLACE`.
+    let object = || {
+        const MODIFIERS: &[&str] = &[
+            "OR",
+            "REPLACE",
+            "UNIQUE",
+            "TEMP",
+            "TEMPORARY",
+            "MATERIALIZED",
+            "GLOBAL",
+            "LOCAL",
+            "UNLOGGED",
+            "RECURSIVE",
+            "FULLTEXT",
+            "SPATIAL",
+        ];
+        (1..words.len())
+            .map(word)
+            .find(|w| !MODIFIERS.contains(w))
+            .unwrap_or("")
+    };
+
+    match word(0) {
+        "SELECT" | "WITH" | "VALUES" => SqlOperation::Select,
+        "INSERT" if word(1) == "OR" && word(2) == "REPLACE" => SqlOperation::Replace,
+        "INSERT" => SqlOperation::Insert,
+        "REPLACE" => SqlOperation::Replace,
+        "UPDATE" => SqlOperation::Update,
+        "DELETE" => SqlOperation::Delete,
+        "MERGE" => SqlOperation::Merge,
+        "CREATE" => match object() {
+            "INDEX" => SqlOperation::CreateIndex,
+            "VIEW" => SqlOperation::CreateView,
+            "SCHEMA" => SqlOperation::CreateSchema,
+            _ => SqlOperation::Create,
+        },
+        "DROP" => match object() {
+            "INDEX" => SqlOperation::DropIndex,
+            "VIEW" => SqlOperation::DropView,
+            "SCHEMA" => SqlOperation::DropSchema,
+            _ => SqlOperation::Drop,
+        },
+        "ALTER" => SqlOperation::Alter,
+        "TRUNCATE" => SqlOperation::Truncate,
+        "CALL" => SqlOperation::Call,
+        "EXPLAIN" | "DESCRIBE" => SqlOperation::Explain,
+        "LOCK" => SqlOperation::Lock,
+        "VACUUM" => SqlOperation::Vacuum,
+        "ANALYZE" | "ANALYSE" => SqlOperation::Analyze,
+        "COPY" => SqlOperation::Copy,
+        "PRAGMA" => SqlOperation::Pragma,
+        "SHOW" => SqlOperation::Show,
+        "GRANT" => SqlOperation::Grant,
+        "REVOKE" => SqlOperation::Revoke,
+        "BEGIN" | "START" => SqlOperation::Begin,
+        "COMMIT" => SqlOperation::Commit,
+        "ROLLBACK" if word(1) == "TO" => SqlOperation::RollbackToSavepoint,
+        "ROLLBACK" => SqlOperation::Rollback,
+        "SAVEPOINT" => SqlOperation::Savepoint,
+        "RELEASE" => SqlOperation::ReleaseSavepoint,
+        "SET" => SqlOperation::Set,
+        _ => SqlOperation::Other,
+    }
+}
+
+/// Skip leading whitespace, comments and opening parentheses.
+fn skip_leading_trivia(mut sql: &str) -> &str {
+    loop {
+        sql = sql.trim_start();
+        if let Some(rest) = sql.strip_prefix("--") {
+            sql = rest.find('\n').map_or("", |end| &rest[end..]);
+        } else if let Some(rest) = sql.strip_prefix("/*") {
+            sql = rest.find("*/").map_or("", |end| &rest[end + 2..]);
+        } else if let Some(rest) = sql.strip_prefix('(') {
+            sql = rest;
+        } else {
+            return sql;
+        }
     }
 }
 
 /// Extract the primary table name from a SQL query.
 ///
+/// Schema-qualified names (`public.users`) return the table part. Unquoted names are
+/// lower-cased; quoted names keep their case.
+///
 /// Returns `None` if the table cannot be determined.
 pub fn extract_table(sql: &str) -> Option<String> {
+    extract_qualified_table(sql).map(|(_, table)| table)
+}
+
+/// Extract the schema (if qualified) and name of the primary table from a SQL query.
+fn extract_qualified_table(sql: &str) -> Option<(Option<String>, String)> {
     let operation = parse_operation(sql);
 
     let regex = match operation {
         SqlOperation::Select => &*SELECT_TABLE_REGEX,
-        SqlOperation::Insert => &*INSERT_TABLE_REGEX,
+        SqlOperation::Insert
+        | SqlOperation::Upsert
+        | SqlOperation::Replace
+        | SqlOperation::Merge => &*INSERT_TABLE_REGEX,
         SqlOperation::Update => &*UPDATE_TABLE_REGEX,
         SqlOperation::Delete => &*DELETE_TABLE_REGEX,
         SqlOperation::Create => &*CREATE_TABLE_REGEX,
@@ -134,38 +310,697 @@ pub fn extract_table(sql: &str) -> Option<String> {
         _ => return None,
     };
 
-    regex
-        .captures(sql)
-        .and_then(|caps| caps.get(1))
-        .map(|m| m.as_str().to_lowercase())
+    let name = regex.captures(sql)?.get(1)?.as_str();
+    let mut parts: Vec<String> = NAME_PART_REGEX
+        .captures_iter(name)
+        .filter_map(|caps| match caps.get(4) {
+            Some(unquoted) => Some(unquoted.as_str().to_lowercase()),
+            None => (1..=3)
+                .find_map(|i| caps.get(i))
+                .map(|m| m.as_str().to_string()),
+        })
+        .collect();
+    let table = parts.pop()?;
+    Some((parts.pop(), table))
 }
 
-/// Parsed SQL information for span creation.
+/// How a statement uses a table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TableRole {
+    /// The statement only reads from the table.
+    Read,
+    /// The statement inserts into, updates, deletes from or changes the table.
+    Written,
+}
+
+/// A table referenced by a statement.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TableRef {
+    /// The schema (or database, on MySQL) qualifying the table, if any.
+    pub schema: Option<String>,
+    pub name: String,
+    pub role: TableRole,
+}
+
+/// Add `table` to `tables`, merging it with an earlier reference to the same table.
+///
+/// A table that is both read and written is reported as written.
+pub(crate) fn add_table(tables: &mut Vec<TableRef>, table: TableRef) {
+    match tables
+        .iter_mut()
+        .find(|t| t.name == table.name && t.schema == table.schema)
+    {
+        Some(existing) => {
+            if table.role == TableRole::Written {
+                existing.role = TableRole::Written;
+            }
+        }
+        None => tables.push(table),
+    }
+}
+
+/// Operation and tables found by one of the statement analyzers.
 #[derive(Debug)]
+pub(crate) struct Analysis {
+    pub operation: SqlOperation,
+    pub schema: Option<String>,
+    pub table: Option<String>,
+    pub tables: Vec<TableRef>,
+    pub stored_procedure: Option<String>,
+}
+
+impl Analysis {
+    /// Analyze `sql` with the regex fallback.
+    fn from_regex(sql: &str) -> Self {
+        let operation = parse_operation(sql);
+        let Some((schema, table)) = extract_qualified_table(sql) else {
+            return Self {
+                operation,
+                schema: None,
+                table: None,
+                tables: Vec::new(),
+                stored_procedure: None,
+            };
+        };
+        let role = match operation {
+            SqlOperation::Select | SqlOperation::Other => TableRole::Read,
+            _ => TableRole::Written,
+        };
+        Self {
+            operation,
+            tables: vec![TableRef {
+                schema: schema.clone(),
+                name: table.clone(),
+                role,
+            }],
+            schema,
+            table: Some(table),
+            stored_procedure: None,
+        }
+    }
+
+    /// Fill in whatever this analysis could not determine from `other`.
+    #[cfg(feature = "sqlparser")]
+    fn or(self, other: Self) -> Self {
+        Self {
+            operation: match self.operation {
+                SqlOperation::Other => other.operation,
+                operation => operation,
+            },
+            schema: if self.table.is_some() {
+                self.schema
+            } else {
+                other.schema
+            },
+            table: self.table.or(other.table),
+            tables: if self.tables.is_empty() {
+                other.tables
+            } else {
+                self.tables
+            },
+            stored_procedure: self.stored_procedure.or(other.stored_procedure),
+        }
+    }
+}
+
+/// Structural facts about a statement.
+///
+/// Clauses are those of the statement itself, not of its subqueries.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct QueryShape {
+    /// Whether the statement has a WHERE clause.
+    pub has_where: bool,
+    /// Whether the statement has a LIMIT or `FETCH FIRST` clause.
+    pub has_limit: bool,
+    /// The row limit, when it is a literal rather than a bound parameter.
+    pub limit: Option<u64>,
+    /// The row offset, when it is a literal rather than a bound parameter.
+    pub offset: Option<u64>,
+    /// Whether the statement has an ORDER BY clause.
+    pub has_order_by: bool,
+    /// Whether the statement is a `SELECT DISTINCT`.
+    pub distinct: bool,
+    /// Whether the statement has a GROUP BY clause.
+    pub has_group_by: bool,
+    /// The row locking clause, if any.
+    pub lock: Option<LockClause>,
+    /// Number of JOINs, including those in subqueries.
+    pub join_count: usize,
+    /// How deeply subqueries are nested; `0` if there are none.
+    pub subquery_depth: usize,
+}
+
+/// A row locking clause.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LockClause {
+    /// `FOR UPDATE`
+    ForUpdate,
+    /// `FOR NO KEY UPDATE` (Postgres)
+    ForNoKeyUpdate,
+    /// `FOR SHARE`, or MySQL `LOCK IN SHARE MODE`
+    ForShare,
+    /// `FOR KEY SHARE` (Postgres)
+    ForKeyShare,
+}
+
+impl LockClause {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LockClause::ForUpdate => "FOR UPDATE",
+            LockClause::ForNoKeyUpdate => "FOR NO KEY UPDATE",
+            LockClause::ForShare => "FOR SHARE",
+            LockClause::ForKeyShare => "FOR KEY SHARE",
+        }
+    }
+}
+
+/// Parsed SQL information for span creation.
+#[derive(Debug, Clone)]
 pub struct ParsedSql {
+    /// The kind of statement, recorded as `db.operation`.
     pub operation: SqlOperation,
+    /// The schema (or database, on MySQL) qualifying the primary table, if any.
+    pub schema: Option<String>,
+    /// The primary table read from or written to.
+    ///
+    /// Unquoted names are lower-cased; quoted names keep their case.
     pub table: Option<String>,
+    /// Every table referenced by the statement, in order of appearance, with its role.
+    pub tables: Vec<TableRef>,
+    /// The procedure invoked by a `CALL` statement.
+    pub stored_procedure: Option<String>,
+    /// The columns named in a `RETURNING` clause, or `None` if the statement has none.
+    ///
+    /// `*` is kept as `*` and aliased expressions are listed by their alias.
+    pub returning: Option<Vec<String>>,
+    /// Structural facts about the statement, such as its WHERE and LIMIT clauses.
+    pub shape: QueryShape,
+    /// Stable hash of the normalized statement, see [`fingerprint`].
+    pub fingerprint: String,
+    span_name: String,
+    span_name_overridden: bool,
 }
 
 impl ParsedSql {
-    /// Parse a SQL statement and extract operation and table information.
-    pub fn parse(sql: &str) -> Self {
-        let operation = parse_operation(sql);
-        let table = extract_table(sql);
-        Self { operation, table }
+    /// Parse a SQL statement and extract operation, table and fingerprint information.
+    pub fn parse(sql: &str, backend: DbBackend) -> Self {
+        let tokens = lexer::significant_tokens(sql, backend);
+        let analysis = analyzer::analyze(&tokens);
+
+        #[cfg(feature = "sqlparser")]
+        let analysis = match (sql_ast::analyze(sql, backend), analysis) {
+            (Some(parsed), Some(lexed)) => Some(parsed.or(lexed)),
+            (parsed, lexed) => parsed.or(lexed),
+        };
+
+        let Analysis {
+            operation,
+            schema,
+            table,
+            tables,
+            stored_procedure,
+        } = analysis.unwrap_or_else(|| Analysis::from_regex(sql));
+
+        let mut parsed = Self {
+            operation,
+            schema,
+            table,
+            tables,
+            stored_procedure,
+            returning: analyzer::returning(&tokens),
+            shape: analyzer::shape(&tokens),
+            fingerprint: hash(&normalize_tokens(&tokens, sql.len())),
+            span_name: String::new(),
+            span_name_overridden: false,
+        };
+        parsed.span_name = parsed.default_span_name();
+        parsed
     }
 
-    /// Generate a span name from the parsed SQL.
+    /// Parse a SQL statement, then let `classifier` adjust the result.
     ///
-    /// Format: "db.query {OPERATION} {table}" or "db.query {OPERATION}"
-    pub fn span_name(&self) -> String {
-        match &self.table {
-            Some(table) => format!("{} {}", self.operation.as_str(), table),
+    /// Unless the classifier sets a span name with [`set_span_name`](Self::set_span_name),
+    /// the span name is derived from the adjusted operation and table.
+    pub fn parse_with(sql: &str, backend: DbBackend, classifier: &dyn SqlClassifier) -> Self {
+        let mut parsed = Self::parse(sql, backend);
+        classifier.classify(sql, backend, &mut parsed);
+        if !parsed.span_name_overridden {
+            parsed.span_name = parsed.default_span_name();
+        }
+        parsed
+    }
+
+    /// The distinct names of all referenced tables, in order of appearance.
+    pub fn collection_names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = Vec::with_capacity(self.tables.len());
+        for table in &self.tables {
+            if !names.contains(&table.name.as_str()) {
+                names.push(&table.name);
+            }
+        }
+        names
+    }
+
+    /// Names of the tables the statement writes to.
+    pub fn tables_written(&self) -> impl Iterator<Item = &str> {
+        self.tables
+            .iter()
+            .filter(|t| t.role == TableRole::Written)
+            .map(|t| t.name.as_str())
+    }
+
+    /// The span name for the parsed SQL, computed once when parsing.
+    ///
+    /// Format: "{OPERATION} {table}" or "{OPERATION}", unless set with
+    /// [`set_span_name`](Self::set_span_name).
+    pub fn span_name(&self) -> &str {
+        &self.span_name
+    }
+
+    /// Override the span name.
+    pub fn set_span_name(&mut self, name: impl Into<String>) {
+        self.span_name = name.into();
+        self.span_name_overridden = true;
+    }
+
+    fn default_span_name(&self) -> String {
+        match self.table.as_ref().or(self.stored_procedure.as_ref()) {
+            Some(name) => format!("{} {}", self.operation.as_str(), name),
             None => self.operation.as_str().to_string(),
         }
     }
 }
 
+/// A hook that overrides or enriches how statements are classified.
+///
+/// The classifier runs after the built-in parser and can change the operation, the
+/// tables and the span name of a statement. Results are cached per connection, so the
+/// classifier should depend only on the SQL text and backend.
+///
+/// Closures taking `(&str, DbBackend, &mut ParsedSql)` implement this trait.
+///
+/// # Example
+///
+/// ```rust
+/// use sea_orm::DbBackend;
+/// use sea_orm_tracing::parser::{ParsedSql, SqlClassifier};
+/// use sea_orm_tracing::TracingConfig;
+///
+/// /// Label `CALL rpt_*` procedures as reports.
+/// struct ReportProcedures;
+///
+/// impl SqlClassifier for ReportProcedures {
+///     fn classify(&self, _sql: &str, _backend: DbBackend, parsed: &mut ParsedSql) {
+///         if let Some(name) = parsed.stored_procedure.as_deref() {
+///             if let Some(report) = name.strip_prefix("rpt_") {
+///                 let span_name = format!("REPORT {}", report);
+///                 parsed.set_span_name(span_name);
+///             }
+///         }
+///     }
+/// }
+///
+/// let config = TracingConfig::default().with_classifier(ReportProcedures);
+///
+/// let sql = "CALL rpt_sales($1)";
+/// let parsed = ParsedSql::parse_with(sql, DbBackend::Postgres, &ReportProcedures);
+/// assert_eq!(parsed.span_name(), "REPORT sales");
+/// ```
+pub trait SqlClassifier: Send + Sync {
+    /// Adjust `parsed`, the built-in classification of `sql`.
+    fn classify(&self, sql: &str, backend: DbBackend, parsed: &mut ParsedSql);
+}
+
+impl<F> SqlClassifier for F
+where
+    F: Fn(&str, DbBackend, &mut ParsedSql) + Send + Sync,
+{
+    fn classify(&self, sql: &str, backend: DbBackend, parsed: &mut ParsedSql) {
+        self(sql, backend, parsed)
+    }
+}
+
+impl std::fmt::Debug for dyn SqlClassifier {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("SqlClassifier")
+    }
+}
+
+/// Replace literal values in `sql` with `?`.
+///
+/// String, numeric, boolean and hex/blob literals are replaced; identifiers, keywords,
+/// comments and bind parameters are kept as written. Quoting follows the rules of
+/// `backend`, so Postgres dollar-quoted strings and MySQL double-quoted strings are
+/// replaced while MySQL backtick and SQLite bracket identifiers are kept.
+pub fn sanitize(sql: &str, backend: DbBackend) -> String {
+    let mut sanitized = String::with_capacity(sql.len());
+    for token in lexer::tokenize(sql, backend) {
+        if is_literal(&token) {
+            sanitized.push('?');
+        } else {
+            sanitized.push_str(token.text);
+        }
+    }
+    sanitized
+}
+
+fn is_literal(token: &Token<'_>) -> bool {
+    match token.kind {
+        TokenKind::String | TokenKind::Number | TokenKind::HexLiteral => true,
+        TokenKind::Word => token.is_keyword("TRUE") || token.is_keyword("FALSE"),
+        _ => false,
+    }
+}
+
+/// Normalize a SQL statement to its query shape.
+///
+/// Literals and bind parameters become `?`, comments are removed, whitespace is
+/// collapsed and unquoted identifiers and keywords are lower-cased. Parenthesized
+/// lists made only of values, such as `IN ($1, $2, $3)`, fold to `(...)`, and the
+/// tuples of a multi-row `VALUES` clause fold to the first tuple. Statements that only
+/// differ in their values, or in how many values they bind, normalize to the same text.
+pub fn normalize(sql: &str, backend: DbBackend) -> String {
+    normalize_tokens(&lexer::significant_tokens(sql, backend), sql.len())
+}
+
+fn normalize_tokens(tokens: &[Token<'_>], capacity: usize) -> String {
+    let words: Vec<String> = tokens
+        .iter()
+        .map(|token| match token.kind {
+            _ if is_literal(token) => "?".to_string(),
+            TokenKind::Placeholder => "?".to_string(),
+            TokenKind::Word => token.text.to_lowercase(),
+            _ => token.text.to_string(),
+        })
+        .collect();
+
+    let mut folded: Vec<&str> = Vec::with_capacity(words.len());
+    let mut extra_tuples = None;
+    let mut i = 0;
+    while i < words.len() {
+        if let Some((from, to)) = extra_tuples {
+            if i == from {
+                extra_tuples = None;
+                i = to;
+                continue;
+            }
+        }
+        if let Some(end) = value_list_end(&words, i) {
+            folded.extend(["(", "...", ")"]);
+            i = end + 1;
+        } else {
+            if words[i] == "values" {
+                extra_tuples = extra_values_tuples(&words, i + 1);
+            }
+            folded.push(&words[i]);
+            i += 1;
+        }
+    }
+
+    let mut normalized = String::with_capacity(capacity);
+    let mut prev = "";
+    for word in folded {
+        let glued = matches!(word, "," | ")" | ".") || matches!(prev, "(" | "." | "");
+        if !glued {
+            normalized.push(' ');
+        }
+        normalized.push_str(word);
+        prev = word;
+    }
+    normalized
+}
+
+/// A stable hash of the [normalized](normalize) statement, as 16 hex digits.
+///
+/// The hash (64-bit FNV-1a) does not depend on the Rust version or process, so
+/// fingerprints can be compared across services and deployments.
+///
+/// ```rust
+/// use sea_orm::DbBackend;
+/// use sea_orm_tracing::fingerprint;
+///
+/// assert_eq!(
+///     fingerprint("SELECT * FROM users WHERE id IN (1, 2, 3)", DbBackend::Postgres),
+///     fingerprint("select *  from users where id in ($1)", DbBackend::Postgres),
+/// );
+/// ```
+pub fn fingerprint(sql: &str, backend: DbBackend) -> String {
+    hash(&normalize(sql, backend))
+}
+
+fn hash(normalized: &str) -> String {
+    format!("{:016x}", fnv1a(normalized.as_bytes()))
+}
+
+/// 64-bit FNV-1a hash of `bytes`.
+pub(crate) fn fnv1a(bytes: &[u8]) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0100_0000_01b3;
+
+    bytes.iter().fold(OFFSET_BASIS, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(PRIME)
+    })
+}
+
+/// If `words[open]` starts a parenthesized list of values (`(?, ?, ?)`), return the
+/// index of its closing parenthesis.
+fn value_list_end(words: &[String], open: usize) -> Option<usize> {
+    if words[open] != "(" {
+        return None;
+    }
+    let mut i = open + 1;
+    loop {
+        if words.get(i)? != "?" {
+            return None;
+        }
+        match words.get(i + 1)?.as_str() {
+            ")" => return Some(i + 1),
+            "," => i += 2,
+            _ => return None,
+        }
+    }
+}
+
+/// If a `VALUES` clause starts at `start`, return the range of tokens after its first
+/// tuple that holds the remaining tuples.
+fn extra_values_tuples(words: &[String], start: usize) -> Option<(usize, usize)> {
+    if words.get(start).map(String::as_str) != Some("(") {
+        return None;
+    }
+    let from = matching_word_paren(words, start) + 1;
+    let mut to = from;
+    while words.get(to).is_some_and(|w| w == ",") && words.get(to + 1).is_some_and(|w| w == "(") {
+        to = matching_word_paren(words, to + 1) + 1;
+    }
+    Some((from, to))
+}
+
+fn matching_word_paren(words: &[String], open: usize) -> usize {
+    let mut depth = 0usize;
+    for (k, word) in words.iter().enumerate().skip(open) {
+        match word.as_str() {
+            "(" => depth += 1,
+            ")" => {
+                depth -= 1;
+                if depth == 0 {
+                    return k;
+                }
+            }
+            _ => {}
+        }
+    }
+    words.len()
+}
+
+/// Map bind parameters to the columns they are bound to.
+///
+/// Returns a vector indexed by zero-based parameter position. Parameters are matched
+/// to columns in INSERT column lists, `SET col = $n` assignments and WHERE
+/// comparisons (`col = $n`, `col IN ($n, ...)`, `col BETWEEN $n AND $m`, ...).
+/// Entries are `None` when the column cannot be determined. Unquoted column names are
+/// lower-cased; qualified names (`u.email`) map to the bare column name.
+pub fn parameter_columns(sql: &str, backend: DbBackend) -> Vec<Option<String>> {
+    let tokens = lexer::significant_tokens(sql, backend);
+    // Resolve the index of every placeholder up front: `$n` / `?n` are numbered,
+    // bare `?` are numbered in order of appearance.
+    let mut next_positional = 0;
+    let indexes: Vec<Option<usize>> = tokens
+        .iter()
+        .map(|t| {
+            if t.kind != TokenKind::Placeholder {
+                return None;
+            }
+            t.placeholder_number().or_else(|| {
+                (t.text == "?").then(|| {
+                    next_positional += 1;
+                    next_positional - 1
+                })
+            })
+        })
+        .collect();
+
+    let count = indexes.iter().flatten().max().map_or(0, |max| max + 1);
+    let mut columns: Vec<Option<String>> = vec![None; count];
+    let mut assign = |index: usize, column: &str| {
+        columns[index] = Some(column.to_string());
+    };
+
+    let mut i = 0;
+    while i < tokens.len() {
+        let token = &tokens[i];
+
+        // INSERT INTO t (a, b) VALUES ($1, $2), ($3, $4)
+        if token.is_keyword("INSERT") {
+            if let Some(next) = map_insert_values(&tokens, &indexes, i, &mut assign) {
+                i = next;
+                continue;
+            }
+        }
+
+        // col = $1, col <> $1, col LIKE $1, col IN ($1, $2), col BETWEEN $1 AND $2
+        if let Some(column) = comparison_column(&tokens, i) {
+            let mut j = i + 1;
+            if tokens.get(j).is_some_and(|t| t.is_keyword("NOT")) {
+                j += 1;
+            }
+            match tokens.get(j) {
+                Some(t)
+                    if t.is_keyword("IN") && tokens.get(j + 1).is_some_and(|t| t.is_punct('(')) =>
+                {
+                    let end = matching_paren(&tokens, j + 1);
+                    for index in indexes[j + 2..end].iter().flatten() {
+                        assign(*index, &column);
+                    }
+                    i = end;
+                    continue;
+                }
+                Some(t) if t.is_keyword("BETWEEN") => {
+                    for k in [j + 1, j + 3] {
+                        if let Some(index) = indexes.get(k).copied().flatten() {
+                            assign(index, &column);
+                        }
+                    }
+                }
+                Some(t) if is_comparison(t) => {
+                    if let Some(index) = indexes.get(j + 1).copied().flatten() {
+                        assign(index, &column);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        i += 1;
+    }
+
+    columns
+}
+
+/// If the token at `i` is a (possibly qualified) column reference followed by a
+/// comparison, return the column name.
+fn comparison_column(tokens: &[Token<'_>], i: usize) -> Option<String> {
+    let token = &tokens[i];
+    if !token.is_identifier()
+        || token.is_keyword("NOT")
+        || tokens.get(i + 1).is_some_and(|t| t.is_punct('.'))
+    {
+        return None;
+    }
+    let mut j = i + 1;
+    if tokens.get(j).is_some_and(|t| t.is_keyword("NOT")) {
+        j += 1;
+    }
+    let next = tokens.get(j)?;
+    let compares = is_comparison(next) || next.is_keyword("IN") || next.is_keyword("BETWEEN");
+    if compares {
+        token.identifier()
+    } else {
+        None
+    }
+}
+
+fn is_comparison(token: &Token<'_>) -> bool {
+    match token.kind {
+        TokenKind::Operator => {
+            matches!(
+                token.text,
+                "=" | "==" | "<>" | "!=" | "<" | ">" | "<=" | ">="
+            )
+        }
+        TokenKind::Word => ["LIKE", "ILIKE", "GLOB", "REGEXP"]
+            .iter()
+            .any(|kw| token.is_keyword(kw)),
+        _ => false,
+    }
+}
+
+/// Return the index of the `)` matching the `(` at `open`, or the end of the tokens.
+fn matching_paren(tokens: &[Token<'_>], open: usize) -> usize {
+    let mut depth = 0usize;
+    for (k, token) in tokens.iter().enumerate().skip(open) {
+        if token.is_punct('(') {
+            depth += 1;
+        } else if token.is_punct(')') {
+            depth -= 1;
+            if depth == 0 {
+                return k;
+            }
+        }
+    }
+    tokens.len()
+}
+
+/// Map the placeholders in the VALUES tuples of an INSERT to its column list.
+///
+/// Returns the index just past the VALUES list, or `None` if the statement has no
+/// column list or VALUES clause.
+fn map_insert_values(
+    tokens: &[Token<'_>],
+    indexes: &[Option<usize>],
+    insert: usize,
+    assign: &mut impl FnMut(usize, &str),
+) -> Option<usize> {
+    let open = (insert..tokens.len())
+        .take_while(|&k| !tokens[k].is_keyword("VALUES") && !tokens[k].is_keyword("SELECT"))
+        .find(|&k| tokens[k].is_punct('('))?;
+    let close = matching_paren(tokens, open);
+    let columns: Vec<Option<String>> = tokens[open + 1..close]
+        .split(|t| t.is_punct(','))
+        .map(|col| col.last().and_then(Token::identifier))
+        .collect();
+
+    if !tokens.get(close + 1)?.is_keyword("VALUES") {
+        return None;
+    }
+
+    let mut k = close + 2;
+    while tokens.get(k).is_some_and(|t| t.is_punct('(')) {
+        let end = matching_paren(tokens, k);
+        let mut position = 0;
+        let mut depth = 0usize;
+        for m in k + 1..end {
+            let token = &tokens[m];
+            if token.is_punct('(') {
+                depth += 1;
+            } else if token.is_punct(')') {
+                depth = depth.saturating_sub(1);
+            } else if token.is_punct(',') && depth == 0 {
+                position += 1;
+            } else if let Some(index) = indexes[m] {
+                if let Some(Some(column)) = columns.get(position) {
+                    assign(index, column);
+                }
+            }
+        }
+        k = end + 1;
+        if tokens.get(k).is_some_and(|t| t.is_punct(',')) {
+            k += 1;
+        }
+    }
+
+    Some(k)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -180,6 +1015,26 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_parse_after_comments_and_ctes() {
+        assert_eq!(
+            parse_operation("WITH old AS (SELECT id FROM t) DELETE FROM t WHERE id IN (1)"),
+            SqlOperation::Delete
+        );
+        assert_eq!(
+            parse_operation("with a as (select 1), b as (select 2) insert into t select * from a"),
+            SqlOperation::Insert
+        );
+        assert_eq!(
+            parse_operation("/* controller:users */ -- note\n  UPDATE users SET a = 1"),
+            SqlOperation::Update
+        );
+        assert_eq!(
+            parse_operation("(SELECT 1) UNION (SELECT 2)"),
+            SqlOperation::Select
+        );
+    }
+
     #[test]
     fn test_parse_insert() {
         assert_eq!(
@@ -212,7 +1067,7 @@ mod tests {
         );
         assert_eq!(
             extract_table(r#"SELECT * FROM "Users" WHERE id = 1"#),
-            Some("users".to_string())
+            Some("Users".to_string())
         );
         assert_eq!(
             extract_table("select u.* from users u join orders o on u.id = o.user_id"),
@@ -220,6 +1075,50 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_extract_qualified_table() {
+        assert_eq!(
+            extract_qualified_table("SELECT * FROM public.users WHERE id = 1"),
+            Some((Some("public".to_string()), "users".to_string()))
+        );
+        assert_eq!(
+            extract_qualified_table(r#"DELETE FROM "my schema"."Orders" WHERE id = $1"#),
+            Some((Some("my schema".to_string()), "Orders".to_string()))
+        );
+        assert_eq!(
+            extract_qualified_table("INSERT INTO `Shop`.`Items` (id) VALUES (?)"),
+            Some((Some("Shop".to_string()), "Items".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_parsed_sql_schema() {
+        let parsed = ParsedSql::parse(
+            r#"UPDATE "Billing".invoices SET paid = true WHERE id = $1"#,
+            DbBackend::Postgres,
+        );
+        assert_eq!(parsed.schema.as_deref(), Some("Billing"));
+        assert_eq!(parsed.table.as_deref(), Some("invoices"));
+        assert_eq!(parsed.span_name(), "UPDATE invoices");
+    }
+
+    #[test]
+    fn test_parsed_sql_tables() {
+        let parsed = ParsedSql::parse(
+            "select u.* from users u join orders o on u.id = o.user_id",
+            DbBackend::Postgres,
+        );
+        assert_eq!(parsed.table.as_deref(), Some("users"));
+        assert_eq!(parsed.collection_names(), ["users", "orders"]);
+
+        let parsed = ParsedSql::parse(
+            "UPDATE app.users SET plan = p.name FROM billing.plans p, app.users u2",
+            DbBackend::Postgres,
+        );
+        assert_eq!(parsed.collection_names(), ["users", "plans"]);
+        assert_eq!(parsed.tables_written().collect::<Vec<_>>(), ["users"]);
+    }
+
     #[test]
     fn test_extract_table_insert() {
         assert_eq!(
@@ -246,11 +1145,100 @@ mod tests {
 
     #[test]
     fn test_parsed_sql_span_name() {
-        let parsed = ParsedSql::parse("SELECT * FROM users WHERE id = 1");
+        let parsed = ParsedSql::parse("SELECT * FROM users WHERE id = 1", DbBackend::Postgres);
         assert_eq!(parsed.span_name(), "SELECT users");
 
-        let parsed = ParsedSql::parse("BEGIN");
+        let parsed = ParsedSql::parse("BEGIN", DbBackend::Postgres);
         assert_eq!(parsed.span_name(), "BEGIN");
+
+        let parsed = ParsedSql::parse("CALL refresh_stats()", DbBackend::Postgres);
+        assert_eq!(parsed.span_name(), "CALL refresh_stats");
+    }
+
+    #[test]
+    fn test_parsed_sql_returning() {
+        let parsed = ParsedSql::parse(
+            r#"INSERT INTO "cake" ("name") VALUES ($1) RETURNING "id", "name""#,
+            DbBackend::Postgres,
+        );
+        assert_eq!(
+            parsed.returning,
+            Some(vec!["id".to_string(), "name".to_string()])
+        );
+
+        let parsed = ParsedSql::parse("INSERT INTO `cake` (`name`) VALUES (?)", DbBackend::MySql);
+        assert_eq!(parsed.returning, None);
+    }
+
+    #[test]
+    fn test_write_variants() {
+        assert_eq!(
+            parse_operation("INSERT INTO t (id) VALUES (1) ON CONFLICT (id) DO NOTHING"),
+            SqlOperation::Upsert
+        );
+        assert_eq!(
+            parse_operation("insert into t values (1) on duplicate key update id = 1"),
+            SqlOperation::Upsert
+        );
+        assert_eq!(
+            parse_operation("INSERT OR REPLACE INTO t VALUES (1)"),
+            SqlOperation::Replace
+        );
+        assert_eq!(
+            parse_operation("REPLACE INTO t VALUES (1)"),
+            SqlOperation::Replace
+        );
+        assert_eq!(
+            parse_operation("MERGE INTO t USING s ON t.id = s.id"),
+            SqlOperation::Merge
+        );
+        assert!(SqlOperation::Upsert.is_write());
+        assert!(SqlOperation::Merge.is_write());
+        assert_eq!(
+            extract_table("INSERT OR REPLACE INTO kv VALUES (1)"),
+            Some("kv".into())
+        );
+    }
+
+    #[test]
+    fn test_utility_and_ddl_operations() {
+        assert_eq!(parse_operation("CALL refresh_stats()"), SqlOperation::Call);
+        assert_eq!(parse_operation("EXPLAIN SELECT 1"), SqlOperation::Explain);
+        assert_eq!(parse_operation("DESCRIBE users"), SqlOperation::Explain);
+        assert_eq!(
+            parse_operation("LOCK TABLES users WRITE"),
+            SqlOperation::Lock
+        );
+        assert_eq!(parse_operation("VACUUM FULL"), SqlOperation::Vacuum);
+        assert_eq!(parse_operation("ANALYZE users"), SqlOperation::Analyze);
+        assert_eq!(parse_operation("COPY users TO STDOUT"), SqlOperation::Copy);
+        assert_eq!(
+            parse_operation("PRAGMA foreign_keys = ON"),
+            SqlOperation::Pragma
+        );
+        assert_eq!(parse_operation("SHOW search_path"), SqlOperation::Show);
+        assert_eq!(
+            parse_operation("GRANT SELECT ON t TO r"),
+            SqlOperation::Grant
+        );
+        assert_eq!(
+            parse_operation("REVOKE ALL ON t FROM r"),
+            SqlOperation::Revoke
+        );
+        assert_eq!(
+            parse_operation("CREATE UNIQUE INDEX idx ON t (id)"),
+            SqlOperation::CreateIndex
+        );
+        assert_eq!(
+            parse_operation("CREATE OR REPLACE VIEW v AS SELECT 1"),
+            SqlOperation::CreateView
+        );
+        assert_eq!(
+            parse_operation("DROP SCHEMA s CASCADE"),
+            SqlOperation::DropSchema
+        );
+        assert_eq!(SqlOperation::CreateIndex.as_str(), "CREATE INDEX");
+        assert!(!SqlOperation::Vacuum.is_write());
     }
 
     #[test]
@@ -260,4 +1248,154 @@ mod tests {
         assert_eq!(parse_operation("COMMIT"), SqlOperation::Commit);
         assert_eq!(parse_operation("ROLLBACK"), SqlOperation::Rollback);
     }
+
+    #[test]
+    fn test_savepoint_operations() {
+        assert_eq!(
+            parse_operation("SAVEPOINT _sqlx_savepoint_1"),
+            SqlOperation::Savepoint
+        );
+        assert_eq!(
+            parse_operation("RELEASE SAVEPOINT _sqlx_savepoint_1"),
+            SqlOperation::ReleaseSavepoint
+        );
+        assert_eq!(
+            parse_operation("ROLLBACK TO SAVEPOINT _sqlx_savepoint_1"),
+            SqlOperation::RollbackToSavepoint
+        );
+
+        let parsed = ParsedSql::parse("release savepoint sp1", DbBackend::Postgres);
+        assert_eq!(parsed.span_name(), "RELEASE SAVEPOINT");
+    }
+
+    #[test]
+    fn test_parameter_columns_insert() {
+        let sql = r#"INSERT INTO "users" ("name", "email") VALUES ($1, $2), ($3, $4)"#;
+        assert_eq!(
+            parameter_columns(sql, DbBackend::Postgres),
+            [
+                Some("name".to_string()),
+                Some("email".to_string()),
+                Some("name".to_string()),
+                Some("email".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parameter_columns_update_and_where() {
+        let sql = "UPDATE `users` SET `password` = ?, `name` = ? WHERE `users`.`id` = ?";
+        assert_eq!(
+            parameter_columns(sql, DbBackend::MySql),
+            [
+                Some("password".to_string()),
+                Some("name".to_string()),
+                Some("id".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parameter_columns_in_and_between() {
+        let sql = "SELECT * FROM users WHERE ssn IN ($1, $2) AND age BETWEEN $3 AND $4 \
+                   AND email NOT LIKE $5 AND lower(name) = $6";
+        assert_eq!(
+            parameter_columns(sql, DbBackend::Postgres),
+            [
+                Some("ssn".to_string()),
+                Some("ssn".to_string()),
+                Some("age".to_string()),
+                Some("age".to_string()),
+                Some("email".to_string()),
+                None,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_sanitize() {
+        assert_eq!(
+            sanitize(
+                "SELECT * FROM users WHERE name = 'O''Brien' AND age > 42 AND active = TRUE",
+                DbBackend::Postgres
+            ),
+            "SELECT * FROM users WHERE name = ? AND age > ? AND active = ?"
+        );
+        // Bind parameters, identifiers and comments are kept
+        assert_eq!(
+            sanitize(
+                "UPDATE \"t1\" SET x = $1, y = -1.5e3 /* note */ WHERE id = $2",
+                DbBackend::Postgres
+            ),
+            "UPDATE \"t1\" SET x = $1, y = -? /* note */ WHERE id = $2"
+        );
+    }
+
+    #[test]
+    fn test_sanitize_backend_quoting() {
+        assert_eq!(
+            sanitize("SELECT $tag$it's $1$tag$, E'a\\'b'", DbBackend::Postgres),
+            "SELECT ?, ?"
+        );
+        assert_eq!(
+            sanitize(
+                "SELECT `name` FROM `t` WHERE a = \"x\" AND b = 0xFF",
+                DbBackend::MySql
+            ),
+            "SELECT `name` FROM `t` WHERE a = ? AND b = ?"
+        );
+        assert_eq!(
+            sanitize(
+                "INSERT INTO [blobs] ([data]) VALUES (X'CAFE')",
+                DbBackend::Sqlite
+            ),
+            "INSERT INTO [blobs] ([data]) VALUES (?)"
+        );
+    }
+
+    #[test]
+    fn test_normalize() {
+        let backend = DbBackend::Postgres;
+        assert_eq!(
+            normalize(
+                "SELECT  u.id, count(*) FROM users u -- c\n \
+                 WHERE u.id IN ($1, $2, $3) AND name = 'x'",
+                backend
+            ),
+            "select u.id, count (*) from users u where u.id in (...) and name = ?"
+        );
+        assert_eq!(
+            normalize(
+                "INSERT INTO t (a, b) VALUES ($1, $2), ($3, $4), ($5, $6)",
+                backend
+            ),
+            "insert into t (a, b) values (...)"
+        );
+        assert_eq!(
+            normalize(
+                "INSERT INTO t (a, b) VALUES ($1, now()), ($2, now())",
+                backend
+            ),
+            "insert into t (a, b) values (?, now ())"
+        );
+    }
+
+    #[test]
+    fn test_fingerprint_is_stable_across_values() {
+        let backend = DbBackend::MySql;
+        let a = fingerprint(
+            "SELECT * FROM users WHERE id IN (1, 2) AND name = 'a'",
+            backend,
+        );
+        let b = fingerprint(
+            "select * from users\n where id in (?, ?, ?) and name = \"b\"",
+            backend,
+        );
+        let c = fingerprint("SELECT * FROM orders WHERE id IN (1, 2)", backend);
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+        assert_eq!(a.len(), 16);
+        // The hash is fixed, not seeded per process
+        assert_eq!(fingerprint("", backend), "cbf29ce484222325");
+    }
 }
@@ -0,0 +1,863 @@
+//! Token-based statement analysis.
+//!
+//! Works on the output of the [lexer](super::lexer), so string literals, comments and
+//! quoted identifiers never confuse operation or table detection.
+
+use super::lexer::{Token, TokenKind};
+use super::{
+    add_table, classify, Analysis, LockClause, QueryShape, SqlOperation, TableRef, TableRole,
+};
+
+/// Words that can follow a table-introducing keyword but never name a table.
+const RESERVED: &[&str] = &[
+    "AS",
+    "DEFAULT",
+    "DO",
+    "FROM",
+    "IF",
+    "IN",
+    "JOIN",
+    "LATERAL",
+    "LIKE",
+    "LIMIT",
+    "NOWAIT",
+    "OF",
+    "ON",
+    "ONLY",
+    "ORDER",
+    "PROGRAM",
+    "RETURNING",
+    "SELECT",
+    "SET",
+    "SKIP",
+    "STDIN",
+    "STDOUT",
+    "TABLE",
+    "TABLES",
+    "UNNEST",
+    "USING",
+    "VALUES",
+    "WHERE",
+    "WITH",
+];
+
+/// Options that can precede the table list of `VACUUM` and `ANALYZE`.
+const MAINTENANCE_OPTIONS: &[&str] = &["ANALYSE", "ANALYZE", "FREEZE", "FULL", "VERBOSE"];
+
+/// Functions whose arguments may contain a `FROM` keyword that is not a table clause,
+/// e.g. `EXTRACT(YEAR FROM created_at)`.
+const FROM_FUNCTIONS: &[&str] = &["EXTRACT", "OVERLAY", "POSITION", "SUBSTRING", "TRIM"];
+
+/// Analyze a statement from its significant tokens.
+///
+/// Returns `None` if the statement does not start with a keyword.
+pub(crate) fn analyze(tokens: &[Token<'_>]) -> Option<Analysis> {
+    let (start, ctes) = main_statement(tokens);
+    let statement = tokens.get(start..)?;
+    let operation = operation(statement)?;
+
+    let mut refs = match operation {
+        // These name privileges, settings or server objects rather than tables
+        SqlOperation::Show | SqlOperation::Grant | SqlOperation::Revoke | SqlOperation::Pragma => {
+            Vec::new()
+        }
+        _ => table_refs(tokens),
+    };
+    // References to common table expressions are not tables
+    refs.retain(|r| r.table.schema.is_some() || !ctes.contains(&r.table.name));
+
+    match operation {
+        SqlOperation::CreateIndex | SqlOperation::DropIndex => {
+            refs.extend(index_table(statement));
+        }
+        // EXPLAIN only plans the statement, so nothing is written
+        SqlOperation::Explain => {
+            for r in &mut refs {
+                r.table.role = TableRole::Read;
+            }
+        }
+        _ => {}
+    }
+
+    let stored_procedure = match operation {
+        SqlOperation::Call => table_name(statement, 1).map(|(_, name, _)| name),
+        _ => None,
+    };
+
+    // The primary table is the first one referenced outside any subquery.
+    let (schema, table) = match refs.iter().min_by_key(|r| r.depth) {
+        Some(r) => (r.table.schema.clone(), Some(r.table.name.clone())),
+        None => match operation {
+            SqlOperation::CreateSchema | SqlOperation::DropSchema => (schema_name(statement), None),
+            _ => (None, None),
+        },
+    };
+    let mut tables = Vec::with_capacity(refs.len());
+    for r in refs {
+        add_table(&mut tables, r.table);
+    }
+
+    Some(Analysis {
+        operation,
+        schema,
+        table,
+        tables,
+        stored_procedure,
+    })
+}
+
+/// Find the first token of the main statement, skipping leading parentheses and any
+/// `WITH` clause. Also returns the names of the common table expressions.
+pub(crate) fn main_statement(tokens: &[Token<'_>]) -> (usize, Vec<String>) {
+    let skip_parens = |mut pos: usize| {
+        while tokens.get(pos).is_some_and(|t| t.is_punct('(')) {
+            pos += 1;
+        }
+        pos
+    };
+    let skip_group = |pos: usize| match tokens.get(pos) {
+        Some(t) if t.is_punct('(') => super::matching_paren(tokens, pos) + 1,
+        _ => pos,
+    };
+
+    let mut pos = skip_parens(0);
+    let mut ctes = Vec::new();
+    if !tokens.get(pos).is_some_and(|t| t.is_keyword("WITH")) {
+        return (pos, ctes);
+    }
+    pos += 1;
+    if tokens.get(pos).is_some_and(|t| t.is_keyword("RECURSIVE")) {
+        pos += 1;
+    }
+
+    // name [(columns)] AS [NOT] [MATERIALIZED] (query) [, ...]
+    while let Some(name) = tokens.get(pos).and_then(Token::identifier) {
+        ctes.push(name);
+        pos = skip_group(pos + 1);
+        while tokens.get(pos).is_some_and(|t| {
+            t.is_keyword("AS") || t.is_keyword("NOT") || t.is_keyword("MATERIALIZED")
+        }) {
+            pos += 1;
+        }
+        pos = skip_group(pos);
+        if !tokens.get(pos).is_some_and(|t| t.is_punct(',')) {
+            break;
+        }
+        pos += 1;
+    }
+
+    (skip_parens(pos), ctes)
+}
+
+/// The columns listed in the top-level `RETURNING` clause, if the statement has one.
+///
+/// Plain and qualified column names are returned by name, `*` as `*` and aliased
+/// expressions by their alias. Unaliased expressions are skipped.
+pub(crate) fn returning(tokens: &[Token<'_>]) -> Option<Vec<String>> {
+    let (start, _) = main_statement(tokens);
+    let mut depth = 0usize;
+    let mut clause = None;
+    for (i, token) in tokens.iter().enumerate().skip(start) {
+        if token.is_punct('(') {
+            depth += 1;
+        } else if token.is_punct(')') {
+            depth = depth.saturating_sub(1);
+        } else if depth == 0 && token.is_keyword("RETURNING") {
+            clause = Some(i + 1);
+            break;
+        }
+    }
+
+    let mut columns = Vec::new();
+    let mut item = clause?;
+    let mut pos = item;
+    depth = 0;
+    loop {
+        let token = tokens.get(pos);
+        match token {
+            Some(t) if t.is_punct('(') => depth += 1,
+            Some(t) if t.is_punct(')') => depth = depth.saturating_sub(1),
+            Some(t) if depth > 0 || !(t.is_punct(',') || t.is_punct(';')) => {}
+            _ => {
+                columns.extend(returned_column(&tokens[item..pos]));
+                if !token.is_some_and(|t| t.is_punct(',')) {
+                    return Some(columns);
+                }
+                item = pos + 1;
+            }
+        }
+        pos += 1;
+    }
+}
+
+/// Structural facts about the main statement.
+///
+/// Clauses are only considered outside parentheses, so a LIMIT inside a subquery does
+/// not make the statement bounded. Joins are counted at any depth.
+pub(crate) fn shape(tokens: &[Token<'_>]) -> QueryShape {
+    let (start, _) = main_statement(tokens);
+    let statement = &tokens[start.min(tokens.len())..];
+    let number = |pos: usize| {
+        statement
+            .get(pos)
+            .filter(|t| t.kind == TokenKind::Number)
+            .and_then(|t| t.text.parse::<u64>().ok())
+    };
+    let keyword = |pos: usize, kw: &str| statement.get(pos).is_some_and(|t| t.is_keyword(kw));
+
+    let mut shape = QueryShape::default();
+    // Whether each open parenthesis starts a subquery
+    let mut parens: Vec<bool> = Vec::new();
+    for (i, token) in statement.iter().enumerate() {
+        if token.is_punct('(') {
+            parens.push(keyword(i + 1, "SELECT") || keyword(i + 1, "WITH"));
+            let depth = parens.iter().filter(|&&subquery| subquery).count();
+            shape.subquery_depth = shape.subquery_depth.max(depth);
+            continue;
+        }
+        if token.is_punct(')') {
+            parens.pop();
+            continue;
+        }
+        if token.is_keyword("JOIN") {
+            shape.join_count += 1;
+        }
+        if !parens.is_empty() || token.kind != TokenKind::Word {
+            continue;
+        }
+
+        match token.text.to_ascii_uppercase().as_str() {
+            "WHERE" => shape.has_where = true,
+            "DISTINCT" if i > 0 && statement[i - 1].is_keyword("SELECT") => shape.distinct = true,
+            "ORDER" if keyword(i + 1, "BY") => shape.has_order_by = true,
+            "GROUP" if keyword(i + 1, "BY") => shape.has_group_by = true,
+            "LIMIT" => {
+                shape.has_limit = true;
+                // MySQL `LIMIT offset, count`
+                if statement.get(i + 2).is_some_and(|t| t.is_punct(',')) {
+                    shape.offset = number(i + 1);
+                    shape.limit = number(i + 3);
+                } else {
+                    shape.limit = number(i + 1);
+                }
+            }
+            // `FETCH FIRST n ROWS ONLY`
+            "FETCH" => {
+                shape.has_limit = true;
+                shape.limit = number(i + 2);
+            }
+            "OFFSET" => shape.offset = number(i + 1),
+            "FOR" => {
+                shape.lock = match statement.get(i + 1).map(|t| t.text.to_ascii_uppercase()) {
+                    Some(kw) if kw == "UPDATE" => Some(LockClause::ForUpdate),
+                    Some(kw) if kw == "SHARE" => Some(LockClause::ForShare),
+                    Some(kw) if kw == "NO" => Some(LockClause::ForNoKeyUpdate),
+                    Some(kw) if kw == "KEY" => Some(LockClause::ForKeyShare),
+                    _ => shape.lock,
+                }
+            }
+            // MySQL `LOCK IN SHARE MODE`
+            "LOCK" if keyword(i + 1, "IN") && keyword(i + 2, "SHARE") => {
+                shape.lock = Some(LockClause::ForShare);
+            }
+            _ => {}
+        }
+    }
+    shape
+}
+
+/// The name under which one `RETURNING` item is returned.
+fn returned_column(item: &[Token<'_>]) -> Option<String> {
+    let last = item.last()?;
+    if last.kind == TokenKind::Operator && last.text == "*" {
+        return Some("*".to_string());
+    }
+    // A (qualified) column name, `expr AS alias` or `expr alias`, but not the type of a
+    // `::` cast or the END of a CASE expression
+    let cast = item.len() > 1 && item[item.len() - 2].text == "::";
+    if cast || last.is_keyword("END") {
+        return None;
+    }
+    last.identifier()
+}
+
+/// Determine the operation from the leading keyword(s).
+fn operation(tokens: &[Token<'_>]) -> Option<SqlOperation> {
+    tokens.first().filter(|t| t.kind == TokenKind::Word)?;
+    let words: Vec<String> = tokens
+        .iter()
+        .take(8)
+        .take_while(|t| t.kind == TokenKind::Word)
+        .map(|t| t.text.to_ascii_uppercase())
+        .collect();
+
+    let operation = match classify(&words) {
+        SqlOperation::Insert if is_upsert(tokens) => SqlOperation::Upsert,
+        operation => operation,
+    };
+    Some(operation)
+}
+
+/// `ON CONFLICT` (Postgres, SQLite) or `ON DUPLICATE KEY` (MySQL).
+fn is_upsert(tokens: &[Token<'_>]) -> bool {
+    tokens.windows(2).any(|pair| {
+        pair[0].is_keyword("ON")
+            && (pair[1].is_keyword("CONFLICT") || pair[1].is_keyword("DUPLICATE"))
+    })
+}
+
+/// The table a `CREATE INDEX ... ON table` or MySQL `DROP INDEX ... ON table` applies to.
+fn index_table(statement: &[Token<'_>]) -> Option<Reference> {
+    let on = statement.iter().position(|t| t.is_keyword("ON"))?;
+    let (schema, name, _) = table_name(statement, on + 1)?;
+    Some(Reference {
+        depth: 0,
+        table: TableRef {
+            schema,
+            name,
+            role: TableRole::Written,
+        },
+    })
+}
+
+/// The schema named by `CREATE SCHEMA` or `DROP SCHEMA`.
+fn schema_name(statement: &[Token<'_>]) -> Option<String> {
+    let keyword = statement.iter().position(|t| t.is_keyword("SCHEMA"))?;
+    table_name(statement, keyword + 1).map(|(_, name, _)| name)
+}
+
+/// A table reference and where it appears.
+struct Reference {
+    /// Parenthesis depth the reference appears at; 0 outside any subquery.
+    depth: usize,
+    table: TableRef,
+}
+
+/// Collect every table reference with the parenthesis depth it appears at.
+fn table_refs(tokens: &[Token<'_>]) -> Vec<Reference> {
+    let mut refs = Vec::new();
+    // One entry per open parenthesis: `true` if it belongs to a FROM_FUNCTIONS call.
+    let mut parens: Vec<bool> = Vec::new();
+
+    for (i, token) in tokens.iter().enumerate() {
+        if token.is_punct('(') {
+            let is_from_function =
+                i > 0 && FROM_FUNCTIONS.iter().any(|f| tokens[i - 1].is_keyword(f));
+            parens.push(is_from_function);
+            continue;
+        }
+        if token.is_punct(')') {
+            parens.pop();
+            continue;
+        }
+        if token.kind != TokenKind::Word || parens.last() == Some(&true) {
+            continue;
+        }
+
+        let prev = i.checked_sub(1).map(|p| &tokens[p]);
+        let mut pos = i + 1;
+        let (is_list, allows_call, role) = match token.text.to_ascii_uppercase().as_str() {
+            // `DELETE FROM t` writes `t`; any other FROM reads
+            "FROM" if prev.is_some_and(is_delete) => (true, false, TableRole::Written),
+            "FROM" => (true, false, TableRole::Read),
+            "JOIN" | "USING" => (false, false, TableRole::Read),
+            "TABLE" if prev.is_some_and(|p| p.is_keyword("LOCK")) => (true, true, TableRole::Read),
+            "TABLE" | "VIEW" => (true, true, TableRole::Written),
+            // Postgres `LOCK name` and MySQL `LOCK TABLES name`
+            "LOCK" | "TABLES" => (true, false, TableRole::Read),
+            "INTO" | "TRUNCATE" => (false, true, TableRole::Written),
+            // `FOR UPDATE`, `ON UPDATE`, `DO UPDATE` and `ON DUPLICATE KEY UPDATE` are not
+            // UPDATE statements
+            "UPDATE"
+                if !prev.is_some_and(|p| {
+                    ["FOR", "ON", "DO", "KEY"].iter().any(|kw| p.is_keyword(kw))
+                }) =>
+            {
+                (false, true, TableRole::Written)
+            }
+            // `EXPLAIN ANALYZE` is an option of EXPLAIN
+            "VACUUM" | "ANALYZE" | "ANALYSE" if !prev.is_some_and(|p| p.is_keyword("EXPLAIN")) => {
+                if tokens.get(pos).is_some_and(|t| t.is_punct('(')) {
+                    pos = super::matching_paren(tokens, pos) + 1;
+                }
+                while tokens
+                    .get(pos)
+                    .is_some_and(|t| MAINTENANCE_OPTIONS.iter().any(|kw| t.is_keyword(kw)))
+                {
+                    pos += 1;
+                }
+                (true, false, TableRole::Read)
+            }
+            // `COPY t FROM ...` loads rows into `t`; `COPY t TO ...` reads them
+            "COPY" => (false, true, copy_role(tokens, pos)),
+            _ => continue,
+        };
+
+        let depth = parens.len();
+        while let Some((schema, table, next)) = table_name(tokens, pos) {
+            // `FROM generate_series(...)` is a function call, not a table
+            if !allows_call && tokens.get(next).is_some_and(|t| t.is_punct('(')) {
+                break;
+            }
+            refs.push(Reference {
+                depth,
+                table: TableRef {
+                    schema,
+                    name: table,
+                    role,
+                },
+            });
+            pos = skip_alias(tokens, next);
+            if !is_list || !tokens.get(pos).is_some_and(|t| t.is_punct(',')) {
+                break;
+            }
+            pos += 1;
+        }
+    }
+
+    refs
+}
+
+/// Read a possibly qualified table name starting at `pos`, skipping `ONLY`,
+/// `IF EXISTS` and `IF NOT EXISTS`. Returns the schema, the table name and the position
+/// after the name.
+fn table_name(tokens: &[Token<'_>], mut pos: usize) -> Option<(Option<String>, String, usize)> {
+    loop {
+        let token = tokens.get(pos)?;
+        if token.is_keyword("ONLY") {
+            pos += 1;
+        } else if token.is_keyword("IF") {
+            pos += 1;
+            while tokens
+                .get(pos)
+                .is_some_and(|t| t.is_keyword("NOT") || t.is_keyword("EXISTS"))
+            {
+                pos += 1;
+            }
+        } else {
+            break;
+        }
+    }
+
+    let mut qualifier = None;
+    loop {
+        let name = tokens.get(pos).filter(|t| is_name(t))?.identifier()?;
+        pos += 1;
+        if tokens.get(pos).is_some_and(|t| t.is_punct('.'))
+            && tokens.get(pos + 1).is_some_and(is_name)
+        {
+            qualifier = Some(name);
+            pos += 1;
+        } else {
+            return Some((qualifier, name, pos));
+        }
+    }
+}
+
+/// The role of the table in `COPY table [(columns)] FROM | TO ...`.
+fn copy_role(tokens: &[Token<'_>], pos: usize) -> TableRole {
+    let Some((_, _, mut next)) = table_name(tokens, pos) else {
+        return TableRole::Read;
+    };
+    if tokens.get(next).is_some_and(|t| t.is_punct('(')) {
+        next = super::matching_paren(tokens, next) + 1;
+    }
+    match tokens.get(next) {
+        Some(t) if t.is_keyword("FROM") => TableRole::Written,
+        _ => TableRole::Read,
+    }
+}
+
+/// `DELETE`, optionally followed by MySQL modifiers, directly before `FROM`.
+fn is_delete(token: &Token<'_>) -> bool {
+    ["DELETE", "IGNORE", "LOW_PRIORITY", "QUICK"]
+        .iter()
+        .any(|kw| token.is_keyword(kw))
+}
+
+/// Skip an optional `[AS] alias` after a table name.
+fn skip_alias(tokens: &[Token<'_>], pos: usize) -> usize {
+    let pos = match tokens.get(pos) {
+        Some(t) if t.is_keyword("AS") => pos + 1,
+        _ => pos,
+    };
+    match tokens.get(pos) {
+        Some(t) if is_name(t) && !is_clause_keyword(t) => pos + 1,
+        _ => pos,
+    }
+}
+
+/// Returns `true` for tokens that can be a table (or alias) name.
+fn is_name(token: &Token<'_>) -> bool {
+    match token.kind {
+        TokenKind::QuotedIdent => true,
+        TokenKind::Word => !RESERVED.iter().any(|kw| token.is_keyword(kw)),
+        _ => false,
+    }
+}
+
+/// Keywords that can directly follow a table name and so are never aliases.
+fn is_clause_keyword(token: &Token<'_>) -> bool {
+    const CLAUSES: &[&str] = &[
+        "CROSS",
+        "EXCEPT",
+        "FETCH",
+        "FOR",
+        "FULL",
+        "GROUP",
+        "HAVING",
+        "INNER",
+        "INTERSECT",
+        "LEFT",
+        "NATURAL",
+        "OFFSET",
+        "OUTER",
+        "RIGHT",
+        "STRAIGHT_JOIN",
+        "UNION",
+        "WINDOW",
+    ];
+    token.kind == TokenKind::Word && CLAUSES.iter().any(|kw| token.is_keyword(kw))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::lexer::significant_tokens;
+    use sea_orm::DbBackend;
+
+    fn analyze_sql(sql: &str) -> Analysis {
+        analyze(&significant_tokens(sql, DbBackend::Postgres)).unwrap()
+    }
+
+    #[test]
+    fn test_ignores_literals_and_comments() {
+        let analysis = analyze_sql(
+            "/* SELECT * FROM audit */ SELECT 'FROM fake' AS note FROM \"users\" -- FROM x",
+        );
+        assert_eq!(analysis.operation, SqlOperation::Select);
+        assert_eq!(analysis.table.as_deref(), Some("users"));
+    }
+
+    #[test]
+    fn test_primary_table_is_outside_subqueries() {
+        let analysis = analyze_sql(
+            "SELECT (SELECT max(total) FROM orders) FROM users u, accounts AS a \
+             LEFT JOIN profiles p ON p.user_id = u.id \
+             WHERE EXTRACT(YEAR FROM u.created_at) = 2024 FOR UPDATE OF u",
+        );
+        assert_eq!(analysis.table.as_deref(), Some("users"));
+    }
+
+    #[test]
+    fn test_all_tables_with_roles() {
+        let names = |analysis: Analysis| -> Vec<(String, TableRole)> {
+            analysis
+                .tables
+                .into_iter()
+                .map(|t| (t.name, t.role))
+                .collect()
+        };
+        let read = |name: &str| (name.to_string(), TableRole::Read);
+        let written = |name: &str| (name.to_string(), TableRole::Written);
+
+        assert_eq!(
+            names(analyze_sql(
+                "select u.* from users u join orders o on u.id = o.user_id"
+            )),
+            [read("users"), read("orders")]
+        );
+        assert_eq!(
+            names(analyze_sql(
+                "INSERT INTO archive (id) SELECT id FROM orders WHERE id IN \
+                 (SELECT order_id FROM refunds)"
+            )),
+            [written("archive"), read("orders"), read("refunds")]
+        );
+        // A table that is both read and written is reported once, as written
+        assert_eq!(
+            names(analyze_sql(
+                "DELETE FROM sessions WHERE user_id IN (SELECT id FROM sessions s)"
+            )),
+            [written("sessions")]
+        );
+    }
+
+    #[test]
+    fn test_write_targets() {
+        let insert = analyze_sql("INSERT INTO orders (id, total) SELECT id, total FROM carts");
+        assert_eq!(insert.operation, SqlOperation::Insert);
+        assert_eq!(insert.table.as_deref(), Some("orders"));
+
+        let update = analyze_sql("UPDATE ONLY users SET name = $1 FROM teams WHERE id = $2");
+        assert_eq!(update.table.as_deref(), Some("users"));
+
+        let drop = analyze_sql("DROP TABLE IF EXISTS sessions, tokens");
+        assert_eq!(drop.operation, SqlOperation::Drop);
+        assert_eq!(drop.table.as_deref(), Some("sessions"));
+    }
+
+    #[test]
+    fn test_control_statements() {
+        assert_eq!(
+            analyze_sql("rollback to savepoint sp1").operation,
+            SqlOperation::RollbackToSavepoint
+        );
+        assert_eq!(
+            analyze_sql("START TRANSACTION").operation,
+            SqlOperation::Begin
+        );
+        assert_eq!(analyze_sql("SELECT 1").table, None);
+        assert!(analyze(&significant_tokens(
+            "-- only a comment",
+            DbBackend::Postgres
+        ))
+        .is_none());
+    }
+
+    #[test]
+    fn test_main_statement_after_ctes() {
+        let analysis = analyze_sql(
+            "WITH stale AS (SELECT id FROM sessions WHERE expires_at < now()) \
+             DELETE FROM sessions WHERE id IN (SELECT id FROM stale)",
+        );
+        assert_eq!(analysis.operation, SqlOperation::Delete);
+        assert_eq!(analysis.table.as_deref(), Some("sessions"));
+        assert_eq!(analysis.tables.len(), 1);
+        assert_eq!(analysis.tables[0].role, TableRole::Written);
+
+        let analysis = analyze_sql(
+            "WITH RECURSIVE tree (id, parent) AS (SELECT id, parent FROM nodes), \
+             totals AS MATERIALIZED (SELECT 1) \
+             INSERT INTO flattened SELECT * FROM tree",
+        );
+        assert_eq!(analysis.operation, SqlOperation::Insert);
+        assert_eq!(analysis.table.as_deref(), Some("flattened"));
+
+        let analysis = analyze_sql("WITH ids AS (SELECT id FROM users) SELECT * FROM ids");
+        assert_eq!(analysis.operation, SqlOperation::Select);
+        assert_eq!(analysis.table.as_deref(), Some("users"));
+    }
+
+    #[test]
+    fn test_leading_comments_and_parens() {
+        let analysis = analyze_sql(
+            "/* app:checkout */ -- trace\n((SELECT id FROM carts) UNION (SELECT id FROM orders))",
+        );
+        assert_eq!(analysis.operation, SqlOperation::Select);
+        assert_eq!(analysis.table.as_deref(), Some("carts"));
+    }
+
+    #[test]
+    fn test_upserts_and_replace() {
+        let upsert = analyze_sql(
+            "INSERT INTO counters (id, n) VALUES ($1, 1) \
+             ON CONFLICT (id) DO UPDATE SET n = counters.n + 1",
+        );
+        assert_eq!(upsert.operation, SqlOperation::Upsert);
+        assert_eq!(upsert.tables.len(), 1);
+
+        let mysql = analyze(&significant_tokens(
+            "INSERT INTO counters (id, n) VALUES (?, 1) ON DUPLICATE KEY UPDATE n = n + 1",
+            DbBackend::MySql,
+        ))
+        .unwrap();
+        assert_eq!(mysql.operation, SqlOperation::Upsert);
+        assert_eq!(mysql.tables.len(), 1);
+
+        let replace = analyze_sql("INSERT OR REPLACE INTO kv (k, v) VALUES (?, ?)");
+        assert_eq!(replace.operation, SqlOperation::Replace);
+        assert_eq!(replace.table.as_deref(), Some("kv"));
+        assert_eq!(
+            analyze_sql("REPLACE INTO kv VALUES (1, 2)").operation,
+            SqlOperation::Replace
+        );
+    }
+
+    #[test]
+    fn test_merge_call_and_explain() {
+        let merge = analyze_sql(
+            "MERGE INTO stock s USING deliveries d ON s.item = d.item \
+             WHEN MATCHED THEN UPDATE SET qty = s.qty + d.qty",
+        );
+        assert_eq!(merge.operation, SqlOperation::Merge);
+        assert_eq!(merge.table.as_deref(), Some("stock"));
+        assert_eq!(merge.tables[1].name, "deliveries");
+        assert_eq!(merge.tables[1].role, TableRole::Read);
+
+        let call = analyze_sql("CALL billing.close_month($1, $2)");
+        assert_eq!(call.operation, SqlOperation::Call);
+        assert_eq!(call.stored_procedure.as_deref(), Some("close_month"));
+        assert_eq!(call.table, None);
+
+        let explain = analyze_sql("EXPLAIN ANALYZE DELETE FROM sessions");
+        assert_eq!(explain.operation, SqlOperation::Explain);
+        assert_eq!(explain.table.as_deref(), Some("sessions"));
+        assert_eq!(explain.tables[0].role, TableRole::Read);
+    }
+
+    #[test]
+    fn test_maintenance_statements() {
+        let vacuum = analyze_sql("VACUUM (VERBOSE) FULL ANALYZE events");
+        assert_eq!(vacuum.operation, SqlOperation::Vacuum);
+        assert_eq!(vacuum.table.as_deref(), Some("events"));
+
+        let lock = analyze_sql("LOCK TABLE accounts IN ACCESS EXCLUSIVE MODE");
+        assert_eq!(lock.operation, SqlOperation::Lock);
+        assert_eq!(lock.table.as_deref(), Some("accounts"));
+
+        let copy_in = analyze_sql("COPY events (id, payload) FROM STDIN WITH (FORMAT csv)");
+        assert_eq!(copy_in.operation, SqlOperation::Copy);
+        assert_eq!(copy_in.tables.len(), 1);
+        assert_eq!(copy_in.tables[0].role, TableRole::Written);
+        let copy_out = analyze_sql("COPY events TO STDOUT");
+        assert_eq!(copy_out.tables[0].role, TableRole::Read);
+
+        assert_eq!(analyze_sql("SHOW TABLES LIKE 'a%'").table, None);
+        assert_eq!(
+            analyze_sql("GRANT SELECT ON users TO reporting")
+                .tables
+                .len(),
+            0
+        );
+        assert_eq!(
+            analyze_sql("PRAGMA table_info(users)").operation,
+            SqlOperation::Pragma
+        );
+    }
+
+    #[test]
+    fn test_ddl_object_kinds() {
+        let index = analyze_sql(
+            "CREATE UNIQUE INDEX CONCURRENTLY IF NOT EXISTS idx_email ON app.users (email)",
+        );
+        assert_eq!(index.operation, SqlOperation::CreateIndex);
+        assert_eq!(index.schema.as_deref(), Some("app"));
+        assert_eq!(index.table.as_deref(), Some("users"));
+
+        let view = analyze_sql("CREATE OR REPLACE VIEW active_users AS SELECT * FROM users");
+        assert_eq!(view.operation, SqlOperation::CreateView);
+        assert_eq!(view.table.as_deref(), Some("active_users"));
+
+        let schema = analyze_sql("CREATE SCHEMA IF NOT EXISTS reporting");
+        assert_eq!(schema.operation, SqlOperation::CreateSchema);
+        assert_eq!(schema.schema.as_deref(), Some("reporting"));
+        assert_eq!(schema.table, None);
+
+        assert_eq!(
+            analyze_sql("DROP INDEX idx_email").operation,
+            SqlOperation::DropIndex
+        );
+        assert_eq!(
+            analyze_sql("DROP MATERIALIZED VIEW stats").operation,
+            SqlOperation::DropView
+        );
+        assert_eq!(
+            analyze_sql("CREATE TABLE t (id int)").operation,
+            SqlOperation::Create
+        );
+    }
+
+    #[test]
+    fn test_returning() {
+        let returning = |sql| returning(&significant_tokens(sql, DbBackend::Postgres));
+        assert_eq!(
+            returning(r#"INSERT INTO users (name) VALUES ($1) RETURNING "id", users.created_at"#),
+            Some(vec!["id".to_string(), "created_at".to_string()])
+        );
+        assert_eq!(
+            returning("DELETE FROM t WHERE id = $1 RETURNING *;"),
+            Some(vec!["*".to_string()])
+        );
+        assert_eq!(
+            returning("UPDATE t SET n = 1 RETURNING n * 2 AS doubled, lower(name) lname, now()"),
+            Some(vec!["doubled".to_string(), "lname".to_string()])
+        );
+        assert_eq!(returning("INSERT INTO t VALUES (1)"), None);
+        // RETURNING inside a CTE does not make the main statement return rows
+        assert_eq!(
+            returning("WITH d AS (DELETE FROM t RETURNING id) SELECT count(*) FROM d"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_shape() {
+        let shape = |sql| shape(&significant_tokens(sql, DbBackend::Postgres));
+
+        let paged = shape(
+            "SELECT DISTINCT u.* FROM users u JOIN orgs o ON o.id = u.org_id \
+             LEFT JOIN teams t ON t.id = u.team_id WHERE o.active \
+             ORDER BY u.id LIMIT 50 OFFSET 10000",
+        );
+        assert_eq!(
+            paged,
+            QueryShape {
+                has_where: true,
+                has_limit: true,
+                limit: Some(50),
+                offset: Some(10000),
+                has_order_by: true,
+                distinct: true,
+                join_count: 2,
+                ..QueryShape::default()
+            }
+        );
+
+        // Bound values are not literal
+        let bound = shape(r#"SELECT "id" FROM "cake" LIMIT $1 OFFSET $2"#);
+        assert!(bound.has_limit);
+        assert_eq!((bound.limit, bound.offset), (None, None));
+
+        let mysql = shape("SELECT * FROM t LIMIT 20, 10 LOCK IN SHARE MODE");
+        assert_eq!((mysql.limit, mysql.offset), (Some(10), Some(20)));
+        assert_eq!(mysql.lock, Some(LockClause::ForShare));
+
+        let fetch = shape("SELECT * FROM t ORDER BY id FETCH FIRST 10 ROWS ONLY");
+        assert_eq!(fetch.limit, Some(10));
+    }
+
+    #[test]
+    fn test_shape_ignores_subqueries() {
+        let shape = |sql| shape(&significant_tokens(sql, DbBackend::Postgres));
+
+        let nested = shape(
+            "SELECT * FROM t WHERE id IN \
+             (SELECT id FROM u WHERE x IN (SELECT x FROM v GROUP BY x) LIMIT 5)",
+        );
+        assert!(nested.has_where);
+        assert!(!nested.has_limit);
+        assert!(!nested.has_group_by);
+        assert_eq!(nested.subquery_depth, 2);
+
+        let unbounded = shape("DELETE FROM sessions");
+        assert!(!unbounded.has_where);
+        assert!(!shape("SELECT 'LIMIT 1' FROM t").has_limit);
+
+        let locked = shape("SELECT * FROM jobs WHERE ready FOR NO KEY UPDATE SKIP LOCKED");
+        assert_eq!(locked.lock, Some(LockClause::ForNoKeyUpdate));
+        let grouped = shape("SELECT a FROM t GROUP BY a FOR UPDATE");
+        assert!(grouped.has_group_by);
+        assert_eq!(grouped.lock, Some(LockClause::ForUpdate));
+    }
+
+    #[test]
+    fn test_qualified_and_quoted_names() {
+        let analysis = analyze_sql(r#"SELECT * FROM "my schema"."Orders" o"#);
+        assert_eq!(analysis.schema.as_deref(), Some("my schema"));
+        assert_eq!(analysis.table.as_deref(), Some("Orders"));
+
+        let analysis = analyze_sql("SELECT * FROM Public.Users");
+        assert_eq!(analysis.schema.as_deref(), Some("public"));
+        assert_eq!(analysis.table.as_deref(), Some("users"));
+
+        let analysis = analyze_sql("SELECT * FROM app.public.users");
+        assert_eq!(analysis.schema.as_deref(), Some("public"));
+    }
+
+    #[test]
+    fn test_function_in_from_is_not_a_table() {
+        let analysis = analyze_sql("SELECT * FROM generate_series(1, 10)");
+        assert_eq!(analysis.table, None);
+    }
+}
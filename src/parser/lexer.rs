@@ -0,0 +1,424 @@
+//! Backend-aware SQL tokenizer.
+//!
+//! The lexer never fails: unterminated strings, quoted identifiers and comments run
+//! to the end of the input, and unknown characters become single-character
+//! operator tokens. Every byte of the input belongs to exactly one token, so the
+//! original SQL can be rebuilt (or rewritten) from the token stream.
+
+use sea_orm::DbBackend;
+
+/// The kind of a lexical token.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum TokenKind {
+    /// Spaces, tabs and newlines.
+    Whitespace,
+    /// `-- line`, `/* block */` and, on MySQL, `# line` comments.
+    Comment,
+    /// Unquoted identifiers and keywords.
+    Word,
+    /// `"ident"`, `` `ident` `` and, on SQLite, `[ident]`.
+    QuotedIdent,
+    /// `'text'`, `E'text'`, `N'text'` and, on Postgres, `$tag$text$tag$`.
+    String,
+    /// Integer and decimal literals.
+    Number,
+    /// `X'0A1B'`, `0x0A1B` and `B'0101'` literals.
+    HexLiteral,
+    /// Bind parameters: `$1`, `?`, `?1`, `:name`, `@name`.
+    Placeholder,
+    /// Operators such as `=`, `<>`, `::` or `->>`.
+    Operator,
+    /// `(`, `)`, `,`, `;`, `.`, `[`, `]`.
+    Punct,
+}
+
+/// A token and its position in the SQL text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Token<'a> {
+    pub kind: TokenKind,
+    pub text: &'a str,
+    pub start: usize,
+}
+
+impl<'a> Token<'a> {
+    /// Returns `true` for whitespace and comments.
+    pub fn is_trivia(&self) -> bool {
+        matches!(self.kind, TokenKind::Whitespace | TokenKind::Comment)
+    }
+
+    /// Returns `true` if this is the given keyword (case-insensitive).
+    pub fn is_keyword(&self, keyword: &str) -> bool {
+        self.kind == TokenKind::Word && self.text.eq_ignore_ascii_case(keyword)
+    }
+
+    /// Returns `true` if this is the given punctuation character.
+    pub fn is_punct(&self, c: char) -> bool {
+        self.kind == TokenKind::Punct && self.text.len() == 1 && self.text.starts_with(c)
+    }
+
+    /// Returns `true` for tokens that can name a table or column.
+    pub fn is_identifier(&self) -> bool {
+        matches!(self.kind, TokenKind::Word | TokenKind::QuotedIdent)
+    }
+
+    /// The identifier this token names.
+    ///
+    /// Unquoted identifiers are folded to lower case; quoted identifiers keep their
+    /// case and have their quotes removed.
+    pub fn identifier(&self) -> Option<String> {
+        match self.kind {
+            TokenKind::Word => Some(self.text.to_lowercase()),
+            TokenKind::QuotedIdent => Some(unquote(self.text)),
+            _ => None,
+        }
+    }
+
+    /// The zero-based index of a positional placeholder (`$1`, `?1`), if numbered.
+    pub fn placeholder_number(&self) -> Option<usize> {
+        if self.kind != TokenKind::Placeholder {
+            return None;
+        }
+        let digits = self.text.strip_prefix(['$', '?'])?;
+        digits.parse::<usize>().ok()?.checked_sub(1)
+    }
+}
+
+/// Remove the quotes from a quoted identifier, collapsing doubled closing quotes.
+fn unquote(text: &str) -> String {
+    let mut chars = text.chars();
+    let (open, close) = match chars.next() {
+        Some('"') => ('"', '"'),
+        Some('`') => ('`', '`'),
+        Some('[') => ('[', ']'),
+        _ => return text.to_string(),
+    };
+    let inner = &text[open.len_utf8()..];
+    let inner = inner.strip_suffix(close).unwrap_or(inner);
+    let doubled = format!("{}{}", close, close);
+    inner.replace(&doubled, &close.to_string())
+}
+
+/// Split `sql` into tokens using the quoting and comment rules of `backend`.
+pub(crate) fn tokenize(sql: &str, backend: DbBackend) -> Vec<Token<'_>> {
+    let bytes = sql.as_bytes();
+    let mut tokens = Vec::new();
+    let mut pos = 0;
+
+    while pos < bytes.len() {
+        let start = pos;
+        let b = bytes[pos];
+        let next = bytes.get(pos + 1).copied();
+
+        let kind = match b {
+            b' ' | b'\t' | b'\n' | b'\r' | b'\x0c' => {
+                pos = skip_while(bytes, pos, |c| c.is_ascii_whitespace());
+                TokenKind::Whitespace
+            }
+            b'-' if next == Some(b'-') => {
+                pos = skip_line(bytes, pos);
+                TokenKind::Comment
+            }
+            b'#' if backend == DbBackend::MySql => {
+                pos = skip_line(bytes, pos);
+                TokenKind::Comment
+            }
+            b'/' if next == Some(b'*') => {
+                pos = match find(bytes, pos + 2, b"*/") {
+                    Some(end) => end + 2,
+                    None => bytes.len(),
+                };
+                TokenKind::Comment
+            }
+            b'\'' => {
+                pos = skip_quoted(bytes, pos, b'\'', backend == DbBackend::MySql);
+                TokenKind::String
+            }
+            b'"' if backend == DbBackend::MySql => {
+                pos = skip_quoted(bytes, pos, b'"', true);
+                TokenKind::String
+            }
+            b'"' => {
+                pos = skip_quoted(bytes, pos, b'"', false);
+                TokenKind::QuotedIdent
+            }
+            b'`' => {
+                pos = skip_quoted(bytes, pos, b'`', false);
+                TokenKind::QuotedIdent
+            }
+            b'[' if backend == DbBackend::Sqlite => {
+                pos = match find(bytes, pos + 1, b"]") {
+                    Some(end) => end + 1,
+                    None => bytes.len(),
+                };
+                TokenKind::QuotedIdent
+            }
+            b'x' | b'X' | b'b' | b'B' if next == Some(b'\'') => {
+                pos = skip_quoted(bytes, pos + 1, b'\'', false);
+                TokenKind::HexLiteral
+            }
+            b'e' | b'E' if next == Some(b'\'') && backend == DbBackend::Postgres => {
+                pos = skip_quoted(bytes, pos + 1, b'\'', true);
+                TokenKind::String
+            }
+            b'n' | b'N' if next == Some(b'\'') => {
+                pos = skip_quoted(bytes, pos + 1, b'\'', backend == DbBackend::MySql);
+                TokenKind::String
+            }
+            b'0' if matches!(next, Some(b'x' | b'X'))
+                && bytes.get(pos + 2).is_some_and(u8::is_ascii_hexdigit) =>
+            {
+                pos = skip_while(bytes, pos + 2, |c| c.is_ascii_hexdigit());
+                TokenKind::HexLiteral
+            }
+            b'0'..=b'9' => {
+                pos = skip_number(bytes, pos);
+                TokenKind::Number
+            }
+            b'.' if next.is_some_and(|c| c.is_ascii_digit()) => {
+                pos = skip_number(bytes, pos);
+                TokenKind::Number
+            }
+            b'$' if next.is_some_and(|c| c.is_ascii_digit()) => {
+                pos = skip_while(bytes, pos + 1, |c| c.is_ascii_digit());
+                TokenKind::Placeholder
+            }
+            b'$' if backend == DbBackend::Postgres => match dollar_quote_end(bytes, pos) {
+                Some(end) => {
+                    pos = end;
+                    TokenKind::String
+                }
+                None => {
+                    pos += 1;
+                    TokenKind::Operator
+                }
+            },
+            b'?' => {
+                pos = skip_while(bytes, pos + 1, |c| c.is_ascii_digit());
+                TokenKind::Placeholder
+            }
+            b':' | b'@' | b'$'
+                if backend == DbBackend::Sqlite && next.is_some_and(is_word_start) =>
+            {
+                pos = skip_while(bytes, pos + 1, is_word_continue);
+                TokenKind::Placeholder
+            }
+            b'(' | b')' | b',' | b';' | b'.' | b'[' | b']' => {
+                pos += 1;
+                TokenKind::Punct
+            }
+            c if is_word_start(c) => {
+                pos = skip_while(bytes, pos, is_word_continue);
+                TokenKind::Word
+            }
+            c if is_operator(c) => {
+                pos = skip_while(bytes, pos, is_operator);
+                TokenKind::Operator
+            }
+            _ => {
+                // Unknown character: consume one full UTF-8 code point.
+                pos += sql[pos..].chars().next().map_or(1, char::len_utf8);
+                TokenKind::Operator
+            }
+        };
+
+        tokens.push(Token {
+            kind,
+            text: &sql[start..pos],
+            start,
+        });
+    }
+
+    tokens
+}
+
+/// Tokenize `sql` and drop whitespace and comments.
+pub(crate) fn significant_tokens(sql: &str, backend: DbBackend) -> Vec<Token<'_>> {
+    tokenize(sql, backend)
+        .into_iter()
+        .filter(|t| !t.is_trivia())
+        .collect()
+}
+
+fn is_word_start(c: u8) -> bool {
+    c.is_ascii_alphabetic() || c == b'_' || c >= 0x80
+}
+
+fn is_word_continue(c: u8) -> bool {
+    c.is_ascii_alphanumeric() || c == b'_' || c == b'$' || c >= 0x80
+}
+
+fn is_operator(c: u8) -> bool {
+    matches!(
+        c,
+        b'<' | b'>'
+            | b'='
+            | b'!'
+            | b'|'
+            | b'&'
+            | b'+'
+            | b'-'
+            | b'*'
+            | b'/'
+            | b'%'
+            | b'^'
+            | b'~'
+            | b'@'
+            | b'#'
+            | b':'
+    )
+}
+
+fn skip_while(bytes: &[u8], mut pos: usize, pred: impl Fn(u8) -> bool) -> usize {
+    while pos < bytes.len() && pred(bytes[pos]) {
+        pos += 1;
+    }
+    pos
+}
+
+fn skip_line(bytes: &[u8], pos: usize) -> usize {
+    skip_while(bytes, pos, |c| c != b'\n')
+}
+
+fn find(bytes: &[u8], from: usize, needle: &[u8]) -> Option<usize> {
+    bytes
+        .get(from..)?
+        .windows(needle.len())
+        .position(|w| w == needle)
+        .map(|i| from + i)
+}
+
+/// Skip a quoted section starting at `pos` (which holds the opening quote).
+///
+/// A doubled quote is an escaped quote. When `backslash_escapes` is set, a
+/// backslash escapes the following byte.
+fn skip_quoted(bytes: &[u8], pos: usize, quote: u8, backslash_escapes: bool) -> usize {
+    let mut pos = pos + 1;
+    while pos < bytes.len() {
+        let c = bytes[pos];
+        if backslash_escapes && c == b'\\' {
+            pos += 2;
+        } else if c == quote {
+            if bytes.get(pos + 1) == Some(&quote) {
+                pos += 2;
+            } else {
+                return pos + 1;
+            }
+        } else {
+            pos += 1;
+        }
+    }
+    bytes.len()
+}
+
+fn skip_number(bytes: &[u8], pos: usize) -> usize {
+    let mut pos = skip_while(bytes, pos, |c| c.is_ascii_digit());
+    if bytes.get(pos) == Some(&b'.') {
+        pos = skip_while(bytes, pos + 1, |c| c.is_ascii_digit());
+    }
+    if matches!(bytes.get(pos), Some(b'e' | b'E')) {
+        let mut exp = pos + 1;
+        if matches!(bytes.get(exp), Some(b'+' | b'-')) {
+            exp += 1;
+        }
+        if bytes.get(exp).is_some_and(u8::is_ascii_digit) {
+            pos = skip_while(bytes, exp, |c| c.is_ascii_digit());
+        }
+    }
+    pos
+}
+
+/// If a Postgres dollar-quoted string (`$$...$$` or `$tag$...$tag$`) starts at `pos`,
+/// return the position just past its end.
+fn dollar_quote_end(bytes: &[u8], pos: usize) -> Option<usize> {
+    let tag_end = skip_while(bytes, pos + 1, |c| c.is_ascii_alphanumeric() || c == b'_');
+    if bytes.get(tag_end) != Some(&b'$') {
+        return None;
+    }
+    let tag = &bytes[pos..=tag_end];
+    match find(bytes, tag_end + 1, tag) {
+        Some(end) => Some(end + tag.len()),
+        None => Some(bytes.len()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(sql: &str, backend: DbBackend) -> Vec<(TokenKind, &str)> {
+        significant_tokens(sql, backend)
+            .into_iter()
+            .map(|t| (t.kind, t.text))
+            .collect()
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let sql = "SELECT \"a\", 'it''s' -- note\nFROM t /* x */ WHERE id = $1";
+        let rebuilt: String = tokenize(sql, DbBackend::Postgres)
+            .iter()
+            .map(|t| t.text)
+            .collect();
+        assert_eq!(rebuilt, sql);
+    }
+
+    #[test]
+    fn test_strings_and_identifiers() {
+        assert_eq!(
+            kinds(r#"SELECT "Users".id, 'a''b' FROM `t`"#, DbBackend::Postgres),
+            [
+                (TokenKind::Word, "SELECT"),
+                (TokenKind::QuotedIdent, "\"Users\""),
+                (TokenKind::Punct, "."),
+                (TokenKind::Word, "id"),
+                (TokenKind::Punct, ","),
+                (TokenKind::String, "'a''b'"),
+                (TokenKind::Word, "FROM"),
+                (TokenKind::QuotedIdent, "`t`"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_backend_specific_quoting() {
+        // MySQL treats double quotes as strings and supports backslash escapes
+        assert_eq!(
+            kinds(r#"SELECT "a\"b""#, DbBackend::MySql)[1],
+            (TokenKind::String, r#""a\"b""#)
+        );
+        // Postgres dollar quoting
+        assert_eq!(
+            kinds("SELECT $fn$ it's $1 $fn$", DbBackend::Postgres)[1],
+            (TokenKind::String, "$fn$ it's $1 $fn$")
+        );
+        // SQLite bracket identifiers and blob literals
+        assert_eq!(
+            kinds("SELECT [my col], X'0A1B'", DbBackend::Sqlite)[1..],
+            [
+                (TokenKind::QuotedIdent, "[my col]"),
+                (TokenKind::Punct, ","),
+                (TokenKind::HexLiteral, "X'0A1B'"),
+            ]
+        );
+        // MySQL hash comments
+        assert_eq!(kinds("SELECT 1 # trailing", DbBackend::MySql).len(), 2);
+    }
+
+    #[test]
+    fn test_placeholders() {
+        let tokens = significant_tokens("a = $2 AND b = ? AND c = ?3", DbBackend::Postgres);
+        let numbers: Vec<_> = tokens
+            .iter()
+            .filter(|t| t.kind == TokenKind::Placeholder)
+            .map(|t| (t.text, t.placeholder_number()))
+            .collect();
+        assert_eq!(numbers, [("$2", Some(1)), ("?", None), ("?3", Some(2))]);
+    }
+
+    #[test]
+    fn test_identifier_case() {
+        let tokens = significant_tokens(r#"Users "Users""#, DbBackend::Postgres);
+        assert_eq!(tokens[0].identifier(), Some("users".to_string()));
+        assert_eq!(tokens[1].identifier(), Some("Users".to_string()));
+    }
+}
@@ -0,0 +1,434 @@
+//! Statement analysis backed by [`sqlparser`], enabled with the `sqlparser` feature.
+
+use std::ops::ControlFlow;
+
+use sea_orm::DbBackend;
+use sqlparser::ast::{
+    visit_relations, FromTable, Ident, Insert, ObjectName, ObjectType, Query, SetExpr,
+    SqliteOnConflict, Statement, TableFactor, TableWithJoins,
+};
+use sqlparser::dialect::{Dialect, MySqlDialect, PostgreSqlDialect, SQLiteDialect};
+use sqlparser::parser::Parser;
+
+use super::{add_table, Analysis, SqlOperation, TableRef, TableRole};
+
+/// Parse `sql` with the dialect of `backend`.
+///
+/// Returns `None` if sqlparser cannot parse the statement.
+pub(crate) fn analyze(sql: &str, backend: DbBackend) -> Option<Analysis> {
+    let dialect: &dyn Dialect = match backend {
+        DbBackend::Postgres => &PostgreSqlDialect {},
+        DbBackend::MySql => &MySqlDialect {},
+        DbBackend::Sqlite => &SQLiteDialect {},
+    };
+    let statements = Parser::parse_sql(dialect, sql).ok()?;
+    let statement = statements.first()?;
+
+    // Common table expressions show up as relations but are not tables.
+    let ctes = match statement {
+        Statement::Query(query) => cte_names(query),
+        _ => Vec::new(),
+    };
+
+    let name = primary_table(statement)
+        .filter(|name| !is_cte(name, &ctes))
+        .cloned()
+        .or_else(|| first_relation(statement, &ctes));
+    let (schema, table) = match &name {
+        Some(name) => qualified_name(name),
+        None => (None, None),
+    };
+
+    let mut tables = Vec::new();
+    // Privileges and settings name objects that are not tables being accessed
+    if !matches!(
+        operation(statement),
+        SqlOperation::Grant | SqlOperation::Revoke | SqlOperation::Show | SqlOperation::Pragma
+    ) {
+        let _ = visit_relations(statement, |name| {
+            if !is_cte(name, &ctes) {
+                add_table(&mut tables, table_ref(name, TableRole::Read));
+            }
+            ControlFlow::<()>::Continue(())
+        });
+    }
+    for target in write_targets(statement) {
+        add_table(&mut tables, table_ref(target, TableRole::Written));
+    }
+
+    let stored_procedure = match statement {
+        Statement::Call(function) => qualified_name(&function.name).1,
+        _ => None,
+    };
+
+    Some(Analysis {
+        operation: operation(statement),
+        schema,
+        table,
+        tables,
+        stored_procedure,
+    })
+}
+
+fn operation(statement: &Statement) -> SqlOperation {
+    match statement {
+        Statement::Query(query) => match query.body.as_ref() {
+            // `WITH ... INSERT` and `WITH ... UPDATE`
+            SetExpr::Insert(statement) | SetExpr::Update(statement) => operation(statement),
+            _ => SqlOperation::Select,
+        },
+        Statement::Insert(insert) => insert_operation(insert),
+        Statement::Update { .. } => SqlOperation::Update,
+        Statement::Delete(_) => SqlOperation::Delete,
+        Statement::Merge { .. } => SqlOperation::Merge,
+        Statement::CreateIndex(_) => SqlOperation::CreateIndex,
+        Statement::CreateView { .. } => SqlOperation::CreateView,
+        Statement::CreateSchema { .. } => SqlOperation::CreateSchema,
+        Statement::CreateTable(_)
+        | Statement::CreateDatabase { .. }
+        | Statement::CreateFunction(_)
+        | Statement::CreateSequence { .. }
+        | Statement::CreateType { .. }
+        | Statement::CreateTrigger { .. }
+        | Statement::CreateVirtualTable { .. } => SqlOperation::Create,
+        Statement::Drop { object_type, .. } => match object_type {
+            ObjectType::Index => SqlOperation::DropIndex,
+            ObjectType::View => SqlOperation::DropView,
+            ObjectType::Schema => SqlOperation::DropSchema,
+            _ => SqlOperation::Drop,
+        },
+        Statement::DropFunction { .. }
+        | Statement::DropProcedure { .. }
+        | Statement::DropTrigger { .. } => SqlOperation::Drop,
+        Statement::AlterTable { .. }
+        | Statement::AlterIndex { .. }
+        | Statement::AlterView { .. }
+        | Statement::AlterRole { .. } => SqlOperation::Alter,
+        Statement::Truncate { .. } => SqlOperation::Truncate,
+        Statement::StartTransaction { .. } => SqlOperation::Begin,
+        Statement::Commit { .. } => SqlOperation::Commit,
+        Statement::Rollback {
+            savepoint: Some(_), ..
+        } => SqlOperation::RollbackToSavepoint,
+        Statement::Rollback { .. } => SqlOperation::Rollback,
+        Statement::Savepoint { .. } => SqlOperation::Savepoint,
+        Statement::ReleaseSavepoint { .. } => SqlOperation::ReleaseSavepoint,
+        Statement::SetVariable { .. }
+        | Statement::SetRole { .. }
+        | Statement::SetTimeZone { .. }
+        | Statement::SetNames { .. }
+        | Statement::SetNamesDefault {}
+        | Statement::SetTransaction { .. } => SqlOperation::Set,
+        Statement::Call(_) => SqlOperation::Call,
+        Statement::Explain { .. } | Statement::ExplainTable { .. } => SqlOperation::Explain,
+        Statement::LockTables { .. } => SqlOperation::Lock,
+        Statement::Analyze { .. } => SqlOperation::Analyze,
+        Statement::Copy { .. } => SqlOperation::Copy,
+        Statement::Pragma { .. } => SqlOperation::Pragma,
+        Statement::ShowTables { .. }
+        | Statement::ShowColumns { .. }
+        | Statement::ShowVariable { .. }
+        | Statement::ShowVariables { .. }
+        | Statement::ShowStatus { .. }
+        | Statement::ShowCreate { .. }
+        | Statement::ShowCollation { .. }
+        | Statement::ShowDatabases { .. }
+        | Statement::ShowSchemas { .. }
+        | Statement::ShowViews { .. }
+        | Statement::ShowFunctions { .. } => SqlOperation::Show,
+        Statement::Grant { .. } => SqlOperation::Grant,
+        Statement::Revoke { .. } => SqlOperation::Revoke,
+        _ => SqlOperation::Other,
+    }
+}
+
+/// Distinguish plain inserts from `REPLACE INTO`, `INSERT OR REPLACE` and upserts.
+fn insert_operation(insert: &Insert) -> SqlOperation {
+    if insert.replace_into || insert.or == Some(SqliteOnConflict::Replace) {
+        SqlOperation::Replace
+    } else if insert.on.is_some() {
+        SqlOperation::Upsert
+    } else {
+        SqlOperation::Insert
+    }
+}
+
+/// The table a statement primarily reads from or writes to.
+fn primary_table(statement: &Statement) -> Option<&ObjectName> {
+    match statement {
+        Statement::Query(query) => query_table(query),
+        Statement::Insert(insert) => Some(&insert.table_name),
+        Statement::Update { table, .. } => relation_table(table),
+        Statement::Delete(delete) => match &delete.from {
+            FromTable::WithFromKeyword(from) | FromTable::WithoutKeyword(from) => {
+                from.first().and_then(relation_table)
+            }
+        },
+        Statement::Merge { table, .. } => factor_table(table),
+        Statement::CreateTable(create) => Some(&create.name),
+        Statement::CreateIndex(create) => Some(&create.table_name),
+        Statement::CreateView { name, .. } => Some(name),
+        Statement::AlterTable { name, .. } => Some(name),
+        Statement::Drop { names, .. } => names.first(),
+        Statement::Truncate { table_names, .. } => table_names.first().map(|t| &t.name),
+        _ => None,
+    }
+}
+
+/// The tables a statement inserts into, updates, deletes from or changes.
+fn write_targets(statement: &Statement) -> Vec<&ObjectName> {
+    match statement {
+        Statement::Query(query) => match query.body.as_ref() {
+            SetExpr::Insert(statement) | SetExpr::Update(statement) => write_targets(statement),
+            _ => Vec::new(),
+        },
+        Statement::Insert(insert) => vec![&insert.table_name],
+        Statement::Update { table, .. } => relation_table(table).into_iter().collect(),
+        Statement::Delete(delete) if !delete.tables.is_empty() => delete.tables.iter().collect(),
+        Statement::Delete(delete) => match &delete.from {
+            FromTable::WithFromKeyword(from) | FromTable::WithoutKeyword(from) => {
+                from.iter().filter_map(relation_table).collect()
+            }
+        },
+        Statement::Merge { table, .. } => factor_table(table).into_iter().collect(),
+        Statement::CreateTable(create) => vec![&create.name],
+        Statement::CreateIndex(create) => vec![&create.table_name],
+        Statement::CreateView { name, .. } => vec![name],
+        Statement::AlterTable { name, .. } => vec![name],
+        Statement::Drop { names, .. } => names.iter().collect(),
+        Statement::Truncate { table_names, .. } => table_names.iter().map(|t| &t.name).collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// The first relation referenced anywhere in the statement, skipping CTE names.
+fn first_relation(statement: &Statement, ctes: &[String]) -> Option<ObjectName> {
+    let found = visit_relations(statement, |name| {
+        if is_cte(name, ctes) {
+            ControlFlow::Continue(())
+        } else {
+            ControlFlow::Break(name.clone())
+        }
+    });
+    match found {
+        ControlFlow::Break(name) => Some(name),
+        ControlFlow::Continue(()) => None,
+    }
+}
+
+fn query_table(query: &Query) -> Option<&ObjectName> {
+    match query.body.as_ref() {
+        SetExpr::Select(select) => select.from.first().and_then(relation_table),
+        SetExpr::Query(query) => query_table(query),
+        SetExpr::SetOperation { left, .. } => set_expr_table(left),
+        SetExpr::Insert(statement) | SetExpr::Update(statement) => primary_table(statement),
+        _ => None,
+    }
+}
+
+fn set_expr_table(expr: &SetExpr) -> Option<&ObjectName> {
+    match expr {
+        SetExpr::Select(select) => select.from.first().and_then(relation_table),
+        SetExpr::Query(query) => query_table(query),
+        _ => None,
+    }
+}
+
+fn relation_table(table: &TableWithJoins) -> Option<&ObjectName> {
+    factor_table(&table.relation)
+}
+
+fn factor_table(factor: &TableFactor) -> Option<&ObjectName> {
+    match factor {
+        TableFactor::Table { name, .. } => Some(name),
+        _ => None,
+    }
+}
+
+fn cte_names(query: &Query) -> Vec<String> {
+    query
+        .with
+        .iter()
+        .flat_map(|with| &with.cte_tables)
+        .map(|cte| identifier(&cte.alias.name))
+        .collect()
+}
+
+fn is_cte(name: &ObjectName, ctes: &[String]) -> bool {
+    name.0.len() == 1 && ctes.contains(&identifier(&name.0[0]))
+}
+
+/// Split an object name into its schema (if qualified) and table name.
+fn qualified_name(name: &ObjectName) -> (Option<String>, Option<String>) {
+    let mut parts = name.0.iter().rev();
+    let table = parts.next().map(identifier);
+    (parts.next().map(identifier), table)
+}
+
+fn table_ref(name: &ObjectName, role: TableRole) -> TableRef {
+    let (schema, table) = qualified_name(name);
+    TableRef {
+        schema,
+        name: table.unwrap_or_default(),
+        role,
+    }
+}
+
+/// Unquoted identifiers are lower-cased; quoted identifiers keep their case.
+fn identifier(ident: &Ident) -> String {
+    match ident.quote_style {
+        Some(_) => ident.value.clone(),
+        None => ident.value.to_lowercase(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_analyze_select() {
+        let analysis = analyze(
+            "WITH recent AS (SELECT * FROM orders) \
+             SELECT * FROM users u JOIN recent r ON r.user_id = u.id",
+            DbBackend::Postgres,
+        )
+        .unwrap();
+        assert_eq!(analysis.operation, SqlOperation::Select);
+        assert_eq!(analysis.table.as_deref(), Some("users"));
+    }
+
+    #[test]
+    fn test_analyze_dialects() {
+        let mysql = analyze("DELETE FROM `Sessions` WHERE id = ?", DbBackend::MySql).unwrap();
+        assert_eq!(mysql.operation, SqlOperation::Delete);
+        assert_eq!(mysql.table.as_deref(), Some("Sessions"));
+
+        let sqlite = analyze("INSERT INTO [logs] (msg) VALUES (?1)", DbBackend::Sqlite).unwrap();
+        assert_eq!(sqlite.operation, SqlOperation::Insert);
+        assert_eq!(sqlite.table.as_deref(), Some("logs"));
+    }
+
+    #[test]
+    fn test_tables_with_roles() {
+        let analysis = analyze(
+            "INSERT INTO archive SELECT * FROM orders o JOIN users u ON u.id = o.user_id",
+            DbBackend::Postgres,
+        )
+        .unwrap();
+        let tables: Vec<_> = analysis
+            .tables
+            .iter()
+            .map(|t| (t.name.as_str(), t.role))
+            .collect();
+        assert_eq!(
+            tables,
+            [
+                ("archive", TableRole::Written),
+                ("orders", TableRole::Read),
+                ("users", TableRole::Read),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_qualified_names() {
+        let analysis = analyze(
+            r#"SELECT * FROM "my schema"."Orders" WHERE id = $1"#,
+            DbBackend::Postgres,
+        )
+        .unwrap();
+        assert_eq!(analysis.schema.as_deref(), Some("my schema"));
+        assert_eq!(analysis.table.as_deref(), Some("Orders"));
+    }
+
+    #[test]
+    fn test_fallback_to_first_relation() {
+        let analysis = analyze(
+            "WITH ids AS (SELECT id FROM users) SELECT * FROM ids",
+            DbBackend::Postgres,
+        )
+        .unwrap();
+        assert_eq!(analysis.table.as_deref(), Some("users"));
+    }
+
+    #[test]
+    fn test_cte_before_insert() {
+        let analysis = analyze(
+            "WITH src AS (SELECT * FROM staging) INSERT INTO items SELECT * FROM src",
+            DbBackend::Postgres,
+        )
+        .unwrap();
+        assert_eq!(analysis.operation, SqlOperation::Insert);
+        assert_eq!(analysis.table.as_deref(), Some("items"));
+    }
+
+    #[test]
+    fn test_operation_variants() {
+        let op = |sql, backend| analyze(sql, backend).unwrap().operation;
+        assert_eq!(
+            op(
+                "INSERT INTO t (id) VALUES (1) ON CONFLICT DO NOTHING",
+                DbBackend::Postgres
+            ),
+            SqlOperation::Upsert
+        );
+        assert_eq!(
+            op(
+                "INSERT INTO t (id) VALUES (1) ON DUPLICATE KEY UPDATE id = 1",
+                DbBackend::MySql
+            ),
+            SqlOperation::Upsert
+        );
+        assert_eq!(
+            op("REPLACE INTO t VALUES (1)", DbBackend::MySql),
+            SqlOperation::Replace
+        );
+        assert_eq!(
+            op("INSERT OR REPLACE INTO t VALUES (1)", DbBackend::Sqlite),
+            SqlOperation::Replace
+        );
+        assert_eq!(
+            op("CREATE INDEX i ON t (id)", DbBackend::Postgres),
+            SqlOperation::CreateIndex
+        );
+        assert_eq!(
+            op("DROP VIEW v", DbBackend::Postgres),
+            SqlOperation::DropView
+        );
+        assert_eq!(
+            op("DROP SCHEMA s", DbBackend::Postgres),
+            SqlOperation::DropSchema
+        );
+        assert_eq!(
+            op("EXPLAIN SELECT 1", DbBackend::Postgres),
+            SqlOperation::Explain
+        );
+        assert_eq!(op("SHOW TABLES", DbBackend::MySql), SqlOperation::Show);
+    }
+
+    #[test]
+    fn test_merge_and_call() {
+        let merge = analyze(
+            "MERGE INTO stock s USING deliveries d ON s.item = d.item \
+             WHEN MATCHED THEN UPDATE SET qty = d.qty",
+            DbBackend::Postgres,
+        )
+        .unwrap();
+        assert_eq!(merge.operation, SqlOperation::Merge);
+        assert_eq!(merge.table.as_deref(), Some("stock"));
+        assert!(merge
+            .tables
+            .iter()
+            .any(|t| t.name == "stock" && t.role == TableRole::Written));
+
+        let call = analyze("CALL close_month(1)", DbBackend::Postgres).unwrap();
+        assert_eq!(call.operation, SqlOperation::Call);
+        assert_eq!(call.stored_procedure.as_deref(), Some("close_month"));
+    }
+
+    #[test]
+    fn test_unparseable_statement() {
+        assert!(analyze("SELEC * FRM users", DbBackend::Postgres).is_none());
+    }
+}
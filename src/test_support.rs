@@ -0,0 +1,92 @@
+//! A subscriber layer that captures span fields, for asserting on recorded attributes.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::subscriber::DefaultGuard;
+use tracing::Subscriber;
+use tracing_subscriber::layer::{Context, SubscriberExt};
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::Layer;
+
+/// The fields recorded on one span.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct CapturedSpan {
+    pub(crate) name: &'static str,
+    pub(crate) fields: HashMap<&'static str, String>,
+}
+
+impl CapturedSpan {
+    /// The recorded value of `field`, formatted as a string.
+    pub(crate) fn get(&self, field: &str) -> Option<&str> {
+        self.fields.get(field).map(String::as_str)
+    }
+}
+
+/// Captures the fields of every span, in creation order.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Capture {
+    spans: Arc<Mutex<Vec<CapturedSpan>>>,
+}
+
+impl Capture {
+    /// Install a capturing subscriber for the current thread.
+    pub(crate) fn install() -> (Self, DefaultGuard) {
+        let capture = Capture::default();
+        let subscriber = tracing_subscriber::registry().with(capture.clone());
+        (capture, tracing::subscriber::set_default(subscriber))
+    }
+
+    /// All captured spans named `name`, in creation order.
+    pub(crate) fn spans(&self, name: &str) -> Vec<CapturedSpan> {
+        let spans = self.spans.lock().unwrap();
+        spans.iter().filter(|s| s.name == name).cloned().collect()
+    }
+}
+
+/// Index of a span in [`Capture::spans`], stored in the span's extensions.
+struct Index(usize);
+
+struct Visitor<'a>(&'a mut HashMap<&'static str, String>);
+
+impl Visit for Visitor<'_> {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name(), value.to_string());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.0.insert(field.name(), format!("{:?}", value));
+    }
+}
+
+impl<S> Layer<S> for Capture
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let mut span = CapturedSpan {
+            name: attrs.metadata().name(),
+            fields: HashMap::new(),
+        };
+        attrs.record(&mut Visitor(&mut span.fields));
+
+        let mut spans = self.spans.lock().unwrap();
+        spans.push(span);
+        if let Some(span) = ctx.span(id) {
+            span.extensions_mut().insert(Index(spans.len() - 1));
+        }
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else { return };
+        let extensions = span.extensions();
+        let Some(Index(index)) = extensions.get::<Index>() else {
+            return;
+        };
+        let mut spans = self.spans.lock().unwrap();
+        values.record(&mut Visitor(&mut spans[*index].fields));
+    }
+}
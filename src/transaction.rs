@@ -0,0 +1,1235 @@
+//! Traced database transaction wrapper.
+
+use std::collections::BTreeSet;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use sea_orm::{
+    AccessMode, ConnectionTrait, DatabaseTransaction, DbBackend, DbErr, ExecResult, IsolationLevel,
+    QueryResult, Statement, StreamTrait, TransactionError, TransactionTrait,
+};
+use tracing::{field, Instrument, Span};
+
+use crate::cache::ParseCache;
+use crate::config::TracingConfig;
+use crate::entity::EntityInfo;
+use crate::instrument::{self, RowCount};
+use crate::parser::ParsedSql;
+
+/// A traced wrapper around SeaORM's `DatabaseTransaction`.
+///
+/// Returned by [`TracedConnection::begin`](crate::TracedConnection::begin) and passed to
+/// the callbacks of [`TracedConnection::transaction`](crate::TracedConnection::transaction).
+/// It shares the configuration of the connection it was started from and instruments
+/// every statement executed inside the transaction, just like `TracedConnection`.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// use sea_orm_tracing::TracedConnection;
+///
+/// let txn = traced_db.begin().await?;
+///
+/// // Traced like any other query
+/// user.insert(&txn).await?;
+///
+/// txn.commit().await?;
+/// ```
+#[derive(Debug)]
+pub struct TracedTransaction {
+    inner: DatabaseTransaction,
+    config: Arc<TracingConfig>,
+    cache: Arc<ParseCache>,
+    depth: Option<u32>,
+    savepoint: Option<String>,
+    savepoints: Option<Savepoints>,
+    lifecycle: Lifecycle,
+}
+
+/// How the driver a transaction runs on names its savepoints.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Savepoints {
+    /// sqlx names them `_sqlx_savepoint_<depth>`.
+    Sqlx,
+    /// SeaORM's mock connection names them `savepoint_<depth>`.
+    Mock,
+}
+
+impl Savepoints {
+    /// Name of the savepoint opened for a transaction nested at `depth`.
+    fn name(&self, depth: u32) -> String {
+        match self {
+            Savepoints::Sqlx => format!("_sqlx_savepoint_{}", depth),
+            Savepoints::Mock => format!("savepoint_{}", depth),
+        }
+    }
+}
+
+/// Outcome of a transaction, recorded as `db.transaction.outcome`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Outcome {
+    Committed,
+    RolledBack,
+    RolledBackOnDrop,
+}
+
+impl Outcome {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Outcome::Committed => "committed",
+            Outcome::RolledBack => "rolled_back",
+            Outcome::RolledBackOnDrop => "rolled_back_on_drop",
+        }
+    }
+}
+
+/// Aggregate statistics for the statements executed inside a transaction.
+///
+/// Statements executed inside a nested transaction also count towards every
+/// enclosing transaction. Their writes only do once the nested transaction commits,
+/// since rolling back to its savepoint undoes them.
+#[derive(Debug)]
+struct TransactionStats {
+    parent: Option<Arc<TransactionStats>>,
+    opened: Instant,
+    statements: AtomicU64,
+    db_time_us: AtomicU64,
+    rows_affected: AtomicU64,
+    tables_written: Mutex<BTreeSet<String>>,
+}
+
+impl TransactionStats {
+    fn new(parent: Option<Arc<TransactionStats>>) -> Self {
+        Self {
+            parent,
+            opened: Instant::now(),
+            statements: AtomicU64::new(0),
+            db_time_us: AtomicU64::new(0),
+            rows_affected: AtomicU64::new(0),
+            tables_written: Mutex::new(BTreeSet::new()),
+        }
+    }
+
+    /// Add a statement executed inside this transaction.
+    fn add(&self, parsed: &ParsedSql, elapsed: Duration, row_count: Option<u64>) {
+        let mut stats = Some(self);
+        while let Some(current) = stats {
+            current.statements.fetch_add(1, Ordering::Relaxed);
+            current
+                .db_time_us
+                .fetch_add(elapsed.as_micros() as u64, Ordering::Relaxed);
+            stats = current.parent.as_deref();
+        }
+
+        if parsed.operation.is_write() {
+            if let Some(count) = row_count {
+                self.rows_affected.fetch_add(count, Ordering::Relaxed);
+            }
+            if let Ok(mut tables) = self.tables_written.lock() {
+                tables.extend(parsed.tables_written().map(str::to_string));
+            }
+        }
+    }
+
+    /// Add the writes of this transaction to the enclosing one, once it commits.
+    fn commit(&self) {
+        let Some(parent) = &self.parent else {
+            return;
+        };
+
+        parent.rows_affected.fetch_add(
+            self.rows_affected.load(Ordering::Relaxed),
+            Ordering::Relaxed,
+        );
+        if let (Ok(tables), Ok(mut parent_tables)) =
+            (self.tables_written.lock(), parent.tables_written.lock())
+        {
+            parent_tables.extend(tables.iter().cloned());
+        }
+    }
+
+    /// Record the statistics on the transaction span.
+    fn record(&self, span: &Span) {
+        let wall_time = self.opened.elapsed();
+        let db_time = Duration::from_micros(self.db_time_us.load(Ordering::Relaxed));
+
+        span.record(
+            "db.transaction.statement_count",
+            self.statements.load(Ordering::Relaxed),
+        );
+        span.record("db.transaction.db_time_ms", db_time.as_millis() as i64);
+        span.record("db.transaction.duration_ms", wall_time.as_millis() as i64);
+        span.record(
+            "db.transaction.idle_time_ms",
+            wall_time.saturating_sub(db_time).as_millis() as i64,
+        );
+        span.record(
+            "db.transaction.rows_affected",
+            self.rows_affected.load(Ordering::Relaxed),
+        );
+
+        if let Ok(tables) = self.tables_written.lock() {
+            if !tables.is_empty() {
+                let tables = tables.iter().map(String::as_str);
+                instrument::record_list(span, "db.transaction.tables_written", tables);
+            }
+        }
+    }
+}
+
+/// The `db.transaction` span covering a transaction from BEGIN until it ends.
+///
+/// If the transaction is dropped without being committed or rolled back, SeaORM rolls
+/// it back in the background; this guard records that outcome on drop.
+#[derive(Debug)]
+struct Lifecycle {
+    span: Span,
+    stats: Arc<TransactionStats>,
+    finished: bool,
+}
+
+impl Lifecycle {
+    /// Record how the transaction ended, along with its aggregate statistics.
+    fn finish(&mut self, outcome: Outcome) {
+        if outcome == Outcome::Committed {
+            self.stats.commit();
+        }
+        self.stats.record(&self.span);
+        self.span.record("db.transaction.outcome", outcome.as_str());
+        self.finished = true;
+    }
+
+    /// Record the result of a COMMIT or ROLLBACK.
+    ///
+    /// A failed COMMIT or ROLLBACK leaves SeaORM to roll back on drop, so the outcome
+    /// is left for the drop guard to record.
+    fn complete(&mut self, result: &Result<(), DbErr>, outcome: Outcome) {
+        match result {
+            Ok(_) => {
+                instrument::record_ok(&self.span);
+                self.finish(outcome);
+            }
+            Err(e) => {
+                instrument::record_error(&self.span, e);
+            }
+        }
+    }
+
+    /// Stop tracking the transaction without recording an outcome.
+    fn detach(&mut self) {
+        self.finished = true;
+    }
+}
+
+impl Drop for Lifecycle {
+    fn drop(&mut self) {
+        if !self.finished {
+            self.finish(Outcome::RolledBackOnDrop);
+        }
+    }
+}
+
+impl TracedTransaction {
+    /// Begin a transaction on `conn` and wrap it.
+    ///
+    /// The returned transaction owns a `db.transaction` span that stays open until the
+    /// transaction is committed, rolled back or dropped. The BEGIN itself is traced as a
+    /// child span.
+    ///
+    /// `depth` is the nesting depth of the new transaction, or `None` when it is nested
+    /// at an unknown depth. Unless it is 0, SeaORM opens a savepoint instead. When
+    /// `parent` is given, `conn` is the parent's inner transaction and the span is nested
+    /// under the parent's span.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) async fn start<C>(
+        conn: &C,
+        config: Arc<TracingConfig>,
+        cache: Arc<ParseCache>,
+        parent: Option<&TracedTransaction>,
+        depth: Option<u32>,
+        isolation_level: Option<IsolationLevel>,
+        access_mode: Option<AccessMode>,
+    ) -> Result<Self, DbErr>
+    where
+        C: ConnectionTrait + TransactionTrait,
+    {
+        let backend = conn.get_database_backend();
+        let parent_stats = parent.map(|p| p.lifecycle.stats.clone());
+
+        let span = match parent {
+            Some(p) => p
+                .lifecycle
+                .span
+                .in_scope(|| transaction_span(&config, backend, "TRANSACTION")),
+            None => transaction_span(&config, backend, "TRANSACTION"),
+        };
+        if let Some(depth) = depth {
+            span.record("db.transaction.depth", depth);
+        }
+        // Only the connection a top-level transaction is begun on tells a mock apart; a
+        // wrapped `DatabaseTransaction` never reports itself as one, so the savepoint
+        // names of transactions nested inside it are unknown
+        let savepoints = match parent {
+            Some(p) => p.savepoints,
+            None if depth == Some(0) && conn.is_mock_connection() => Some(Savepoints::Mock),
+            None if depth == Some(0) => Some(Savepoints::Sqlx),
+            None => None,
+        };
+        let nested = depth != Some(0);
+        let savepoint = depth
+            .filter(|&depth| depth > 0)
+            .zip(savepoints)
+            .map(|(depth, savepoints)| savepoints.name(depth));
+        if let Some(name) = &savepoint {
+            span.record("db.transaction.savepoint", name.as_str());
+        }
+        record_transaction_config(&span, isolation_level, access_mode);
+
+        let operation = if nested { "SAVEPOINT" } else { "BEGIN" };
+        let begin_span = control_span(&config, backend, &span, operation, savepoint.as_deref());
+        let start = Instant::now();
+
+        let result = conn
+            .begin_with_config(isolation_level, access_mode)
+            .instrument(begin_span.clone())
+            .await;
+
+        instrument::record_result(&config, &begin_span, &result, start, None);
+
+        if let Err(e) = &result {
+            instrument::record_error(&span, e);
+        }
+
+        result.map(|inner| Self {
+            inner,
+            config,
+            cache,
+            depth,
+            savepoint,
+            savepoints,
+            lifecycle: Lifecycle {
+                span,
+                stats: Arc::new(TransactionStats::new(parent_stats)),
+                finished: false,
+            },
+        })
+    }
+
+    /// Run `callback` inside this transaction, committing on success and rolling back
+    /// on error.
+    pub(crate) async fn run<F, T, E>(self, callback: F) -> Result<T, TransactionError<E>>
+    where
+        F: for<'c> FnOnce(
+                &'c TracedTransaction,
+            ) -> Pin<Box<dyn Future<Output = Result<T, E>> + Send + 'c>>
+            + Send,
+        T: Send,
+        E: std::fmt::Display + std::fmt::Debug + Send,
+    {
+        let res = callback(&self)
+            .instrument(self.lifecycle.span.clone())
+            .await
+            .map_err(TransactionError::Transaction);
+        if res.is_ok() {
+            self.commit().await.map_err(TransactionError::Connection)?;
+        } else {
+            self.rollback()
+                .await
+                .map_err(TransactionError::Connection)?;
+        }
+        res
+    }
+
+    /// Get a reference to the underlying `DatabaseTransaction`.
+    pub fn inner(&self) -> &DatabaseTransaction {
+        &self.inner
+    }
+
+    /// Get the tracing configuration.
+    pub fn config(&self) -> &TracingConfig {
+        &self.config
+    }
+
+    /// Get the `db.transaction` span covering this transaction.
+    pub fn span(&self) -> &Span {
+        &self.lifecycle.span
+    }
+
+    /// Consume the wrapper and return the inner `DatabaseTransaction`.
+    ///
+    /// The `db.transaction` span ends here without an outcome, since the transaction
+    /// is no longer tracked.
+    pub fn into_inner(mut self) -> DatabaseTransaction {
+        self.lifecycle.detach();
+        self.inner
+    }
+
+    /// Get the nesting depth of this transaction.
+    ///
+    /// Top-level transactions have depth 0; each nested `begin` adds one. The depth is
+    /// `None` for transactions begun on a `DatabaseTransaction` wrapped without one (see
+    /// [`TracedConnection::from_transaction`](crate::TracedConnection::from_transaction)).
+    pub fn depth(&self) -> Option<u32> {
+        self.depth
+    }
+
+    /// Whether this transaction is nested inside another, with a savepoint.
+    fn is_nested(&self) -> bool {
+        self.depth != Some(0)
+    }
+
+    /// Commit the transaction.
+    ///
+    /// For nested transactions this releases the savepoint. The COMMIT is traced as a
+    /// child of the `db.transaction` span, which then ends with
+    /// `db.transaction.outcome = "committed"` and its aggregate statistics recorded.
+    pub async fn commit(mut self) -> Result<(), DbErr> {
+        let backend = self.inner.get_database_backend();
+        let operation = if self.is_nested() {
+            "RELEASE SAVEPOINT"
+        } else {
+            "COMMIT"
+        };
+        let span = control_span(
+            &self.config,
+            backend,
+            &self.lifecycle.span,
+            operation,
+            self.savepoint.as_deref(),
+        );
+        let start = Instant::now();
+
+        let result = self.inner.commit().instrument(span.clone()).await;
+
+        instrument::record_result(&self.config, &span, &result, start, None);
+        self.lifecycle.complete(&result, Outcome::Committed);
+
+        result
+    }
+
+    /// Roll back the transaction.
+    ///
+    /// For nested transactions this rolls back to the savepoint. The ROLLBACK is traced
+    /// as a child of the `db.transaction` span, which then ends with
+    /// `db.transaction.outcome = "rolled_back"` and its aggregate statistics recorded.
+    pub async fn rollback(mut self) -> Result<(), DbErr> {
+        let backend = self.inner.get_database_backend();
+        let operation = if self.is_nested() {
+            "ROLLBACK TO SAVEPOINT"
+        } else {
+            "ROLLBACK"
+        };
+        let span = control_span(
+            &self.config,
+            backend,
+            &self.lifecycle.span,
+            operation,
+            self.savepoint.as_deref(),
+        );
+        let start = Instant::now();
+
+        let result = self.inner.rollback().instrument(span.clone()).await;
+
+        instrument::record_result(&self.config, &span, &result, start, None);
+        self.lifecycle.complete(&result, Outcome::RolledBack);
+
+        result
+    }
+
+    /// Begin a traced nested transaction.
+    ///
+    /// SeaORM implements nested transactions with savepoints. The nested transaction
+    /// gets its own `db.transaction` span, nested under this one, with
+    /// `db.transaction.depth` recorded.
+    ///
+    /// Method-call syntax (`txn.begin()`) resolves to this method rather than
+    /// [`TransactionTrait::begin`], which must return a raw `DatabaseTransaction`.
+    pub async fn begin(&self) -> Result<TracedTransaction, DbErr> {
+        Self::start(
+            &self.inner,
+            self.config.clone(),
+            self.cache.clone(),
+            Some(self),
+            self.depth.map(|depth| depth + 1),
+            None,
+            None,
+        )
+        .await
+    }
+
+    /// Begin a traced nested transaction with isolation level and/or access mode.
+    pub async fn begin_with_config(
+        &self,
+        isolation_level: Option<IsolationLevel>,
+        access_mode: Option<AccessMode>,
+    ) -> Result<TracedTransaction, DbErr> {
+        Self::start(
+            &self.inner,
+            self.config.clone(),
+            self.cache.clone(),
+            Some(self),
+            self.depth.map(|depth| depth + 1),
+            isolation_level,
+            access_mode,
+        )
+        .await
+    }
+
+    /// Execute the callback inside a traced nested transaction.
+    pub async fn transaction<F, T, E>(&self, callback: F) -> Result<T, TransactionError<E>>
+    where
+        F: for<'c> FnOnce(
+                &'c TracedTransaction,
+            ) -> Pin<Box<dyn Future<Output = Result<T, E>> + Send + 'c>>
+            + Send,
+        T: Send,
+        E: std::fmt::Display + std::fmt::Debug + Send,
+    {
+        let transaction = self.begin().await.map_err(TransactionError::Connection)?;
+        transaction.run(callback).await
+    }
+
+    /// Execute the callback inside a traced nested transaction with isolation level
+    /// and/or access mode.
+    pub async fn transaction_with_config<F, T, E>(
+        &self,
+        callback: F,
+        isolation_level: Option<IsolationLevel>,
+        access_mode: Option<AccessMode>,
+    ) -> Result<T, TransactionError<E>>
+    where
+        F: for<'c> FnOnce(
+                &'c TracedTransaction,
+            ) -> Pin<Box<dyn Future<Output = Result<T, E>> + Send + 'c>>
+            + Send,
+        T: Send,
+        E: std::fmt::Display + std::fmt::Debug + Send,
+    {
+        let transaction = self
+            .begin_with_config(isolation_level, access_mode)
+            .await
+            .map_err(TransactionError::Connection)?;
+        transaction.run(callback).await
+    }
+
+    /// Create a tracing span for a database operation, nested under the transaction span.
+    fn create_span(&self, stmt: &Statement, entity: Option<&EntityInfo>) -> (Span, Arc<ParsedSql>) {
+        let backend = self.inner.get_database_backend();
+        let parsed = self.cache.parse(&stmt.sql, backend);
+        let span = self
+            .lifecycle
+            .span
+            .in_scope(|| instrument::create_span(&self.config, backend, stmt, &parsed));
+        if let Some(entity) = entity {
+            instrument::record_entity(&span, &parsed, entity);
+        }
+        (span, parsed)
+    }
+
+    /// Execute a statement, recording `entity` metadata on its span when given.
+    pub(crate) async fn execute_traced(
+        &self,
+        stmt: Statement,
+        entity: Option<&EntityInfo>,
+    ) -> Result<ExecResult, DbErr> {
+        let (span, parsed) = self.create_span(&stmt, entity);
+        let start = Instant::now();
+
+        let result = self.inner.execute(stmt).instrument(span.clone()).await;
+
+        let row_count = result
+            .as_ref()
+            .ok()
+            .map(|r| RowCount::Affected(r.rows_affected()));
+        instrument::record_last_insert_id(&span, self.get_database_backend(), &result);
+        self.record_result(&span, &parsed, &result, start, row_count);
+
+        result
+    }
+
+    /// Query one row, recording `entity` metadata on its span when given.
+    pub(crate) async fn query_one_traced(
+        &self,
+        stmt: Statement,
+        entity: Option<&EntityInfo>,
+    ) -> Result<Option<QueryResult>, DbErr> {
+        let (span, parsed) = self.create_span(&stmt, entity);
+        let start = Instant::now();
+
+        let result = self.inner.query_one(stmt).instrument(span.clone()).await;
+
+        let row_count = result
+            .as_ref()
+            .ok()
+            .map(|opt| RowCount::Returned(opt.is_some() as u64));
+        self.record_result(&span, &parsed, &result, start, row_count);
+
+        result
+    }
+
+    /// Query all rows, recording `entity` metadata on its span when given.
+    pub(crate) async fn query_all_traced(
+        &self,
+        stmt: Statement,
+        entity: Option<&EntityInfo>,
+    ) -> Result<Vec<QueryResult>, DbErr> {
+        let (span, parsed) = self.create_span(&stmt, entity);
+        let start = Instant::now();
+
+        let result = self.inner.query_all(stmt).instrument(span.clone()).await;
+
+        let row_count = result
+            .as_ref()
+            .ok()
+            .map(|rows| RowCount::Returned(rows.len() as u64));
+        self.record_result(&span, &parsed, &result, start, row_count);
+
+        result
+    }
+
+    /// Record the result of a database operation in the span and add it to the
+    /// transaction statistics.
+    fn record_result<T>(
+        &self,
+        span: &Span,
+        parsed: &ParsedSql,
+        result: &Result<T, DbErr>,
+        start: Instant,
+        row_count: Option<RowCount>,
+    ) {
+        let elapsed = instrument::record_result(&self.config, span, result, start, row_count);
+        self.lifecycle
+            .stats
+            .add(parsed, elapsed, row_count.map(RowCount::get));
+    }
+}
+
+/// Create a `db.transaction` span for the `name` operation (TRANSACTION).
+///
+/// The depth and statistics fields are only recorded on the spans of
+/// [`TracedTransaction`]s; the outcome is also recorded for closures run through
+/// [`TransactionTrait::transaction`].
+pub(crate) fn transaction_span(config: &TracingConfig, backend: DbBackend, name: &str) -> Span {
+    let span = tracing::info_span!(
+        "db.transaction",
+        otel.name = name,
+        otel.kind = "client",
+        db.system = field::Empty,
+        db.system.name = field::Empty,
+        db.operation = field::Empty,
+        db.operation.name = field::Empty,
+        db.transaction.depth = field::Empty,
+        db.transaction.savepoint = field::Empty,
+        db.transaction.isolation_level = field::Empty,
+        db.transaction.access_mode = field::Empty,
+        db.transaction.outcome = field::Empty,
+        db.transaction.statement_count = field::Empty,
+        db.transaction.db_time_ms = field::Empty,
+        db.transaction.duration_ms = field::Empty,
+        db.transaction.idle_time_ms = field::Empty,
+        db.transaction.rows_affected = field::Empty,
+        db.transaction.tables_written = field::Empty,
+        otel.status_code = field::Empty,
+        error.type = field::Empty,
+        error.retryable = field::Empty,
+        db.response.status_code = field::Empty,
+        error.message = field::Empty,
+    );
+    instrument::record_system(config, &span, backend, name);
+    span
+}
+
+/// Begin a raw `DatabaseTransaction` for [`TransactionTrait::begin`] and
+/// [`TransactionTrait::begin_with_config`].
+///
+/// The returned transaction is not tracked, so there is no `db.transaction` span: the
+/// BEGIN (or the SAVEPOINT, when `nested`) is traced as a control span under `parent`.
+pub(crate) async fn begin_detached<C: ConnectionTrait + TransactionTrait>(
+    conn: &C,
+    config: &TracingConfig,
+    parent: &Span,
+    nested: bool,
+    isolation_level: Option<IsolationLevel>,
+    access_mode: Option<AccessMode>,
+) -> Result<DatabaseTransaction, DbErr> {
+    let operation = if nested { "SAVEPOINT" } else { "BEGIN" };
+    let span = control_span(config, conn.get_database_backend(), parent, operation, None);
+    record_transaction_config(&span, isolation_level, access_mode);
+    let start = Instant::now();
+
+    let result = conn
+        .begin_with_config(isolation_level, access_mode)
+        .instrument(span.clone())
+        .await;
+
+    instrument::record_result(config, &span, &result, start, None);
+
+    result
+}
+
+/// Record how a transaction run through [`TransactionTrait::transaction`] ended.
+///
+/// SeaORM commits when the closure succeeds and rolls back when it fails. A connection
+/// error leaves the outcome unknown, since it may come from BEGIN, COMMIT or ROLLBACK.
+pub(crate) fn record_closure_result<T, E: std::fmt::Debug>(
+    span: &Span,
+    result: &Result<T, TransactionError<E>>,
+) {
+    match result {
+        Ok(_) => {
+            instrument::record_ok(span);
+            span.record("db.transaction.outcome", Outcome::Committed.as_str());
+        }
+        Err(e) => {
+            instrument::record_transaction_error(span, e);
+            if let TransactionError::Transaction(_) = e {
+                span.record("db.transaction.outcome", Outcome::RolledBack.as_str());
+            }
+        }
+    }
+}
+
+/// Record the isolation level and access mode a transaction was started with.
+pub(crate) fn record_transaction_config(
+    span: &Span,
+    isolation_level: Option<IsolationLevel>,
+    access_mode: Option<AccessMode>,
+) {
+    if let Some(level) = isolation_level {
+        span.record("db.transaction.isolation_level", field::debug(level));
+    }
+    if let Some(mode) = access_mode {
+        span.record("db.transaction.access_mode", field::debug(mode));
+    }
+}
+
+/// Create a span for a transaction control operation, nested under `parent`.
+///
+/// The statement text is not recorded: the driver issues it itself, and may not issue
+/// one at all (sqlx skips BEGIN on some pooled connections), so only the operation
+/// names the span. Savepoint operations record the savepoint's name.
+fn control_span(
+    config: &TracingConfig,
+    backend: DbBackend,
+    parent: &Span,
+    operation: &'static str,
+    savepoint: Option<&str>,
+) -> Span {
+    let span = parent.in_scope(|| {
+        tracing::info_span!(
+            "db.query",
+            otel.name = operation,
+            otel.kind = "client",
+            db.system = field::Empty,
+            db.system.name = field::Empty,
+            db.operation = field::Empty,
+            db.operation.name = field::Empty,
+            db.transaction.savepoint = savepoint,
+            db.transaction.isolation_level = field::Empty,
+            db.transaction.access_mode = field::Empty,
+            db.duration_ms = field::Empty,
+            db.name = field::Empty,
+            server.address = field::Empty,
+            server.port = field::Empty,
+            peer.service = field::Empty,
+            otel.status_code = field::Empty,
+            error.type = field::Empty,
+            error.retryable = field::Empty,
+            db.response.status_code = field::Empty,
+            error.message = field::Empty,
+            slow_query = field::Empty,
+        )
+    });
+    instrument::record_system(config, &span, backend, operation);
+    instrument::record_connection(config, &span);
+    span
+}
+
+impl AsRef<DatabaseTransaction> for TracedTransaction {
+    fn as_ref(&self) -> &DatabaseTransaction {
+        &self.inner
+    }
+}
+
+#[async_trait]
+impl ConnectionTrait for TracedTransaction {
+    fn get_database_backend(&self) -> DbBackend {
+        self.inner.get_database_backend()
+    }
+
+    async fn execute(&self, stmt: Statement) -> Result<ExecResult, DbErr> {
+        self.execute_traced(stmt, None).await
+    }
+
+    async fn execute_unprepared(&self, sql: &str) -> Result<ExecResult, DbErr> {
+        let stmt = Statement::from_string(self.get_database_backend(), sql);
+        let (span, parsed) = self.create_span(&stmt, None);
+        let start = Instant::now();
+
+        let result = self
+            .inner
+            .execute_unprepared(sql)
+            .instrument(span.clone())
+            .await;
+
+        let row_count = result
+            .as_ref()
+            .ok()
+            .map(|r| RowCount::Affected(r.rows_affected()));
+        instrument::record_last_insert_id(&span, self.get_database_backend(), &result);
+        self.record_result(&span, &parsed, &result, start, row_count);
+
+        result
+    }
+
+    async fn query_one(&self, stmt: Statement) -> Result<Option<QueryResult>, DbErr> {
+        self.query_one_traced(stmt, None).await
+    }
+
+    async fn query_all(&self, stmt: Statement) -> Result<Vec<QueryResult>, DbErr> {
+        self.query_all_traced(stmt, None).await
+    }
+
+    fn support_returning(&self) -> bool {
+        self.inner.support_returning()
+    }
+
+    fn is_mock_connection(&self) -> bool {
+        self.inner.is_mock_connection()
+    }
+}
+
+#[async_trait]
+impl StreamTrait for TracedTransaction {
+    type Stream<'a> = <DatabaseTransaction as StreamTrait>::Stream<'a>;
+
+    fn stream<'a>(
+        &'a self,
+        stmt: Statement,
+    ) -> Pin<Box<dyn Future<Output = Result<Self::Stream<'a>, DbErr>> + 'a + Send>> {
+        let (span, parsed) = self.create_span(&stmt, None);
+        let start = Instant::now();
+        let config = self.config.clone();
+
+        Box::pin(async move {
+            let result = self.inner.stream(stmt).instrument(span.clone()).await;
+
+            // Record basic result info (we can't know row count for streams)
+            let elapsed = instrument::record_stream_result(&config, &span, &result, start);
+            self.lifecycle.stats.add(&parsed, elapsed, None);
+
+            result
+        })
+    }
+}
+
+/// Raw `TransactionTrait` implementation for generic code.
+///
+/// The trait requires nested transactions to be plain `DatabaseTransaction`s, so
+/// statements executed inside them are not traced. Prefer the inherent methods of the
+/// same name, which return [`TracedTransaction`].
+#[async_trait]
+impl TransactionTrait for TracedTransaction {
+    async fn begin(&self) -> Result<DatabaseTransaction, DbErr> {
+        begin_detached(
+            &self.inner,
+            &self.config,
+            &self.lifecycle.span,
+            true,
+            None,
+            None,
+        )
+        .await
+    }
+
+    async fn begin_with_config(
+        &self,
+        isolation_level: Option<IsolationLevel>,
+        access_mode: Option<AccessMode>,
+    ) -> Result<DatabaseTransaction, DbErr> {
+        begin_detached(
+            &self.inner,
+            &self.config,
+            &self.lifecycle.span,
+            true,
+            isolation_level,
+            access_mode,
+        )
+        .await
+    }
+
+    async fn transaction<F, T, E>(&self, callback: F) -> Result<T, TransactionError<E>>
+    where
+        F: for<'c> FnOnce(
+                &'c DatabaseTransaction,
+            ) -> Pin<Box<dyn Future<Output = Result<T, E>> + Send + 'c>>
+            + Send,
+        T: Send,
+        E: std::fmt::Display + std::fmt::Debug + Send,
+    {
+        let span = transaction_span(&self.config, self.get_database_backend(), "TRANSACTION");
+
+        let result = self
+            .inner
+            .transaction(callback)
+            .instrument(span.clone())
+            .await;
+
+        record_closure_result(&span, &result);
+
+        result
+    }
+
+    async fn transaction_with_config<F, T, E>(
+        &self,
+        callback: F,
+        isolation_level: Option<IsolationLevel>,
+        access_mode: Option<AccessMode>,
+    ) -> Result<T, TransactionError<E>>
+    where
+        F: for<'c> FnOnce(
+                &'c DatabaseTransaction,
+            ) -> Pin<Box<dyn Future<Output = Result<T, E>> + Send + 'c>>
+            + Send,
+        T: Send,
+        E: std::fmt::Display + std::fmt::Debug + Send,
+    {
+        let span = transaction_span(&self.config, self.get_database_backend(), "TRANSACTION");
+        record_transaction_config(&span, isolation_level, access_mode);
+
+        let result = self
+            .inner
+            .transaction_with_config(callback, isolation_level, access_mode)
+            .instrument(span.clone())
+            .await;
+
+        record_closure_result(&span, &result);
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sea_orm::{MockDatabase, MockExecResult, Transaction};
+
+    use super::*;
+    use crate::test_support::Capture;
+    use crate::TracedConnection;
+
+    fn mock_connection() -> TracedConnection {
+        let db = MockDatabase::new(DbBackend::Postgres)
+            .append_exec_results([MockExecResult {
+                last_insert_id: 0,
+                rows_affected: 1,
+            }])
+            .into_connection();
+        TracedConnection::new(db, TracingConfig::default())
+    }
+
+    #[tokio::test]
+    async fn test_begin_returns_traced_transaction() {
+        let db = mock_connection();
+
+        let txn = db.begin().await.unwrap();
+        assert_eq!(txn.depth(), Some(0));
+        let result = txn
+            .execute_unprepared("UPDATE users SET active = true")
+            .await
+            .unwrap();
+        assert_eq!(result.rows_affected(), 1);
+        txn.commit().await.unwrap();
+
+        assert_eq!(
+            db.into_inner().into_transaction_log(),
+            [Transaction::many([
+                Statement::from_string(DbBackend::Postgres, "BEGIN"),
+                Statement::from_string(DbBackend::Postgres, "UPDATE users SET active = true"),
+                Statement::from_string(DbBackend::Postgres, "COMMIT"),
+            ])]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_transaction_callback_receives_traced_transaction() {
+        let db = mock_connection();
+
+        let rows = db
+            .transaction::<_, _, DbErr>(|txn| {
+                Box::pin(async move {
+                    let result = txn.execute_unprepared("DELETE FROM sessions").await?;
+                    Ok(result.rows_affected())
+                })
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(rows, 1);
+    }
+
+    #[tokio::test]
+    async fn test_nested_transaction_depth() {
+        let (capture, _guard) = Capture::install();
+        let db = mock_connection();
+
+        let txn = db.begin().await.unwrap();
+        let nested = txn.begin().await.unwrap();
+        assert_eq!(nested.depth(), Some(1));
+        nested.rollback().await.unwrap();
+        txn.commit().await.unwrap();
+
+        let depths: Vec<_> = capture
+            .spans("db.transaction")
+            .iter()
+            .map(|span| span.get("db.transaction.depth").map(str::to_string))
+            .collect();
+        assert_eq!(depths, [Some("0".to_string()), Some("1".to_string())]);
+    }
+
+    #[tokio::test]
+    async fn test_savepoint_name_recorded() {
+        let (capture, _guard) = Capture::install();
+        let db = mock_connection();
+
+        let txn = db.begin().await.unwrap();
+        let nested = txn.begin().await.unwrap();
+        nested.rollback().await.unwrap();
+        txn.commit().await.unwrap();
+
+        let transactions = capture.spans("db.transaction");
+        assert_eq!(transactions[0].get("db.transaction.savepoint"), None);
+        assert_eq!(
+            transactions[1].get("db.transaction.savepoint"),
+            Some("savepoint_1")
+        );
+
+        // The name matches the statements the mock connection logged
+        let savepoints: Vec<_> = capture
+            .spans("db.query")
+            .iter()
+            .map(|span| span.get("db.transaction.savepoint").map(str::to_string))
+            .collect();
+        assert_eq!(
+            savepoints,
+            [
+                None,
+                Some("savepoint_1".to_string()),
+                Some("savepoint_1".to_string()),
+                None,
+            ]
+        );
+        assert_eq!(
+            db.into_inner().into_transaction_log()[0].statements()[2].sql,
+            "ROLLBACK TO SAVEPOINT savepoint_1"
+        );
+        assert_eq!(Savepoints::Sqlx.name(2), "_sqlx_savepoint_2");
+    }
+
+    #[tokio::test]
+    async fn test_outcome_recorded_on_span() {
+        let (capture, _guard) = Capture::install();
+        let db = mock_connection();
+
+        db.begin().await.unwrap().commit().await.unwrap();
+        db.begin().await.unwrap().rollback().await.unwrap();
+        drop(db.begin().await.unwrap());
+        // Detached transactions are no longer tracked and get no outcome
+        db.begin()
+            .await
+            .unwrap()
+            .into_inner()
+            .commit()
+            .await
+            .unwrap();
+
+        let outcomes: Vec<_> = capture
+            .spans("db.transaction")
+            .iter()
+            .map(|span| span.get("db.transaction.outcome").map(str::to_string))
+            .collect();
+        assert_eq!(
+            outcomes,
+            [
+                Some("committed".to_string()),
+                Some("rolled_back".to_string()),
+                Some("rolled_back_on_drop".to_string()),
+                None,
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_control_spans_record_no_statement() {
+        let (capture, _guard) = Capture::install();
+        let db = MockDatabase::new(DbBackend::Postgres).into_connection();
+        let config = TracingConfig::default().with_statement_logging(true);
+        let db = TracedConnection::new(db, config);
+
+        db.begin().await.unwrap().commit().await.unwrap();
+
+        let spans = capture.spans("db.query");
+        let operations: Vec<_> = spans.iter().map(|span| span.get("db.operation")).collect();
+        assert_eq!(operations, [Some("BEGIN"), Some("COMMIT")]);
+        assert!(spans.iter().all(|span| span.get("db.statement").is_none()));
+    }
+
+    #[tokio::test]
+    async fn test_stats_recorded_on_span() {
+        let (capture, _guard) = Capture::install();
+        let db = MockDatabase::new(DbBackend::Postgres)
+            .append_exec_results([
+                MockExecResult {
+                    last_insert_id: 0,
+                    rows_affected: 2,
+                },
+                MockExecResult {
+                    last_insert_id: 0,
+                    rows_affected: 3,
+                },
+            ])
+            .into_connection();
+        let db = TracedConnection::new(db, TracingConfig::default());
+
+        let txn = db.begin().await.unwrap();
+        txn.execute_unprepared("INSERT INTO orders (id) VALUES (1)")
+            .await
+            .unwrap();
+        let nested = txn.begin().await.unwrap();
+        nested
+            .execute_unprepared("UPDATE users SET active = false")
+            .await
+            .unwrap();
+
+        // Statistics are only recorded when the transaction ends
+        let spans = capture.spans("db.transaction");
+        assert_eq!(spans[1].get("db.transaction.statement_count"), None);
+
+        nested.commit().await.unwrap();
+        txn.commit().await.unwrap();
+
+        let spans = capture.spans("db.transaction");
+        let (outer, inner) = (&spans[0], &spans[1]);
+        assert_eq!(inner.get("db.transaction.statement_count"), Some("1"));
+        assert_eq!(inner.get("db.transaction.rows_affected"), Some("3"));
+        // The nested transaction's statements roll up into the outer span
+        assert_eq!(outer.get("db.transaction.statement_count"), Some("2"));
+        assert_eq!(outer.get("db.transaction.rows_affected"), Some("5"));
+        // List attributes are exported as arrays with the `opentelemetry` feature
+        #[cfg(not(feature = "opentelemetry"))]
+        {
+            assert_eq!(inner.get("db.transaction.tables_written"), Some("users"));
+            assert_eq!(
+                outer.get("db.transaction.tables_written"),
+                Some("orders,users")
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rolled_back_writes_not_counted() {
+        let (capture, _guard) = Capture::install();
+        let db = MockDatabase::new(DbBackend::Postgres)
+            .append_exec_results([
+                MockExecResult {
+                    last_insert_id: 0,
+                    rows_affected: 2,
+                },
+                MockExecResult {
+                    last_insert_id: 0,
+                    rows_affected: 3,
+                },
+            ])
+            .into_connection();
+        let db = TracedConnection::new(db, TracingConfig::default());
+
+        let txn = db.begin().await.unwrap();
+        txn.execute_unprepared("INSERT INTO orders (id) VALUES (1)")
+            .await
+            .unwrap();
+        let nested = txn.begin().await.unwrap();
+        nested
+            .execute_unprepared("UPDATE users SET active = false")
+            .await
+            .unwrap();
+        nested.rollback().await.unwrap();
+        txn.commit().await.unwrap();
+
+        let spans = capture.spans("db.transaction");
+        let (outer, inner) = (&spans[0], &spans[1]);
+        assert_eq!(inner.get("db.transaction.rows_affected"), Some("3"));
+        // The statement still ran inside the outer transaction, but its writes were undone
+        assert_eq!(outer.get("db.transaction.statement_count"), Some("2"));
+        assert_eq!(outer.get("db.transaction.rows_affected"), Some("2"));
+        #[cfg(not(feature = "opentelemetry"))]
+        assert_eq!(outer.get("db.transaction.tables_written"), Some("orders"));
+    }
+
+    #[tokio::test]
+    async fn test_closure_error_is_application() {
+        let (capture, _guard) = Capture::install();
+        let db = mock_connection();
+
+        let result = TransactionTrait::transaction::<_, (), String>(&db, |_| {
+            Box::pin(async { Err("insufficient funds".to_string()) })
+        })
+        .await;
+        assert!(matches!(result, Err(TransactionError::Transaction(_))));
+
+        let span = &capture.spans("db.transaction")[0];
+        assert_eq!(span.get("error.type"), Some("application"));
+        assert_eq!(span.get("error.retryable"), Some("false"));
+    }
+
+    #[test]
+    fn test_stats_aggregate_writes() {
+        let stats = TransactionStats::new(None);
+        let elapsed = Duration::from_millis(2);
+
+        let parse = |sql| ParsedSql::parse(sql, DbBackend::Postgres);
+
+        stats.add(&parse("SELECT * FROM users"), elapsed, Some(10));
+        stats.add(
+            &parse("INSERT INTO orders (id) VALUES ($1)"),
+            elapsed,
+            Some(1),
+        );
+        stats.add(&parse("UPDATE users SET name = $1"), elapsed, Some(3));
+        stats.add(&parse("UPDATE users SET name = $1"), elapsed, Some(2));
+
+        assert_eq!(stats.statements.load(Ordering::Relaxed), 4);
+        assert_eq!(stats.db_time_us.load(Ordering::Relaxed), 8_000);
+        assert_eq!(stats.rows_affected.load(Ordering::Relaxed), 6);
+        assert_eq!(
+            stats
+                .tables_written
+                .lock()
+                .unwrap()
+                .iter()
+                .collect::<Vec<_>>(),
+            ["orders", "users"]
+        );
+    }
+
+    #[test]
+    fn test_nested_stats_roll_up() {
+        let outer = Arc::new(TransactionStats::new(None));
+        let inner = TransactionStats::new(Some(outer.clone()));
+
+        inner.add(
+            &ParsedSql::parse("DELETE FROM sessions", DbBackend::Postgres),
+            Duration::from_millis(1),
+            Some(5),
+        );
+
+        assert_eq!(inner.statements.load(Ordering::Relaxed), 1);
+        assert_eq!(outer.statements.load(Ordering::Relaxed), 1);
+        // Writes roll up when the nested transaction commits
+        assert_eq!(outer.rows_affected.load(Ordering::Relaxed), 0);
+        inner.commit();
+        assert_eq!(outer.rows_affected.load(Ordering::Relaxed), 5);
+    }
+}